@@ -0,0 +1,158 @@
+use actix_web::dev::ServiceRequest;
+use actix_web::HttpResponse;
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use crate::list;
+
+/// A range of addresses such as `192.168.1.0/24`. A plain address matches
+/// only itself.
+#[derive(Clone, Debug)]
+pub struct Cidr {
+	addr: IpAddr,
+	prefix: u32,
+}
+
+impl Cidr {
+	pub fn contains(&self, ip: IpAddr) -> bool {
+		match (self.addr, ip.to_canonical()) {
+			(IpAddr::V4(net), IpAddr::V4(ip)) => {
+				same_prefix(u32::from(net).into(), u32::from(ip).into(), self.prefix, 32)
+			}
+			(IpAddr::V6(net), IpAddr::V6(ip)) => {
+				same_prefix(net.into(), ip.into(), self.prefix, 128)
+			}
+			_ => false,
+		}
+	}
+}
+
+impl fmt::Display for Cidr {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}/{}", self.addr, self.prefix)
+	}
+}
+
+impl FromStr for Cidr {
+	type Err = ();
+
+	fn from_str(cidr: &str) -> Result<Self, Self::Err> {
+		let (addr, prefix) = match cidr.split_once('/') {
+			Some((addr, prefix)) => (addr, Some(prefix)),
+			None => (cidr, None),
+		};
+		let addr = addr.parse::<IpAddr>().map_err(|_| ())?.to_canonical();
+		let bits = if addr.is_ipv4() { 32 } else { 128 };
+		let prefix = match prefix {
+			Some(prefix) => prefix.parse::<u32>().map_err(|_| ())?,
+			None => bits,
+		};
+		if prefix > bits {
+			return Err(());
+		}
+
+		Ok(Cidr { addr, prefix })
+	}
+}
+
+/// Whether the first `prefix` of the `bits` bits of both addresses match.
+fn same_prefix(a: u128, b: u128, prefix: u32, bits: u32) -> bool {
+	prefix == 0 || a >> (bits - prefix) == b >> (bits - prefix)
+}
+
+/// Parses a comma separated list of ranges such as `192.168.1.0/24,::1`.
+pub fn parse_list(list: &str) -> Result<Vec<Cidr>, String> {
+	list::parse(list, |cidr| cidr.parse().ok())
+}
+
+/// The `403` a request is rejected with when the client address is in
+/// `deny`, or when `allow` is set and the address isn't in it. Deny takes
+/// precedence.
+pub fn check(req: &ServiceRequest, allow: Option<&[Cidr]>, deny: &[Cidr]) -> Option<HttpResponse> {
+	if allow.is_none() && deny.is_empty() {
+		return None;
+	}
+
+	let permitted = req.peer_addr().is_some_and(|peer| {
+		let ip = peer.ip();
+		!deny.iter().any(|cidr| cidr.contains(ip))
+			&& allow.is_none_or(|allow| allow.iter().any(|cidr| cidr.contains(ip)))
+	});
+	if permitted {
+		return None;
+	}
+
+	log::debug!("rejecting request from {:?}", req.peer_addr());
+	Some(HttpResponse::Forbidden().finish())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use actix_web::test::TestRequest;
+
+	fn ip(addr: &str) -> IpAddr {
+		addr.parse().unwrap()
+	}
+
+	#[test]
+	fn parses_ranges() {
+		assert_eq!("192.168.1.0/24".parse::<Cidr>().unwrap().to_string(), "192.168.1.0/24");
+		assert_eq!("::1".parse::<Cidr>().unwrap().to_string(), "::1/128");
+		assert_eq!("::ffff:10.0.0.1".parse::<Cidr>().unwrap().to_string(), "10.0.0.1/32");
+	}
+
+	#[test]
+	fn rejects_malformed_ranges() {
+		assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+		assert!("::/129".parse::<Cidr>().is_err());
+		assert!("10.0.0.0/x".parse::<Cidr>().is_err());
+		assert!("localhost".parse::<Cidr>().is_err());
+	}
+
+	#[test]
+	fn matches_addresses_in_range() {
+		let cidr: Cidr = "192.168.1.0/24".parse().unwrap();
+		assert!(cidr.contains(ip("192.168.1.42")));
+		assert!(!cidr.contains(ip("192.168.2.1")));
+		assert!(!cidr.contains(ip("::1")));
+	}
+
+	#[test]
+	fn matches_ipv4_mapped_addresses() {
+		let cidr: Cidr = "127.0.0.1".parse().unwrap();
+		assert!(cidr.contains(ip("::ffff:127.0.0.1")));
+	}
+
+	#[test]
+	fn zero_prefix_matches_everything() {
+		assert!(same_prefix(0, u32::MAX.into(), 0, 32));
+		assert!(same_prefix(0, u128::MAX, 0, 128));
+		assert!("0.0.0.0/0".parse::<Cidr>().unwrap().contains(ip("203.0.113.7")));
+	}
+
+	#[test]
+	fn compares_only_the_prefix() {
+		assert!(same_prefix(0b1010 << 28, 0b1011 << 28, 3, 32));
+		assert!(!same_prefix(0b1010 << 28, 0b1011 << 28, 4, 32));
+		assert!(same_prefix(7, 7, 128, 128));
+	}
+
+	#[test]
+	fn allows_and_denies_clients() {
+		let allow = parse_list("10.0.0.0/8").unwrap();
+		let deny = parse_list("10.0.0.13").unwrap();
+		let request = |addr: &str| {
+			TestRequest::default()
+				.peer_addr(addr.parse().unwrap())
+				.to_srv_request()
+		};
+
+		assert!(check(&request("10.1.2.3:4000"), Some(&allow), &deny).is_none());
+		let denied = check(&request("10.0.0.13:4000"), Some(&allow), &deny).unwrap();
+		assert_eq!(denied.status(), 403);
+		let outside = check(&request("192.168.0.1:4000"), Some(&allow), &deny).unwrap();
+		assert_eq!(outside.status(), 403);
+	}
+}
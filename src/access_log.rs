@@ -0,0 +1,100 @@
+/// The format of actix-web's default `Logger`.
+pub const DEFAULT_FORMAT: &str = r#"%a "%r" %s %b "%{Referer}i" "%{User-Agent}i" %T"#;
+
+/// Checks an access log format for tokens actix-web's `Logger` doesn't know,
+/// which it would otherwise print literally. Returns the first unknown token.
+///
+/// Custom replacements, `%{NAME}xi` and `%{NAME}xo`, are not supported as
+/// msaada registers none.
+pub fn validate(format: &str) -> Result<(), String> {
+	let mut rest = format;
+	while let Some(start) = rest.find('%') {
+		let token = &rest[start..];
+		let len = token_len(token).ok_or_else(|| shown(token).to_string())?;
+		rest = &token[len..];
+	}
+
+	Ok(())
+}
+
+/// The unknown token at the start of `token`, for the error message: up to
+/// the character after a closing brace, or else the character after `%`.
+fn shown(token: &str) -> &str {
+	let end = match token.find('}') {
+		Some(brace) if token[1..].starts_with('{') => brace + 1,
+		_ => 1,
+	};
+	token[end..]
+		.chars()
+		.next()
+		.map_or(token, |next| &token[..end + next.len_utf8()])
+}
+
+/// The length of the token at the start of `token`, which starts with `%`.
+fn token_len(token: &str) -> Option<usize> {
+	let after = &token[1..];
+	if after.starts_with(['%', 'a', 't', 'P', 'r', 'U', 's', 'b', 'T', 'D']) {
+		return Some(2);
+	}
+
+	let name = after.strip_prefix('{')?;
+	let end = name.find('}')?;
+	let valid_name = end > 0
+		&& name[..end]
+			.chars()
+			.all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+	let kind = name[end + 1..].chars().next()?;
+	// `%{r}a`, the real client address, is the only address variant.
+	let valid_kind = match kind {
+		'a' => &name[..end] == "r",
+		'i' | 'o' | 'e' => true,
+		_ => false,
+	};
+	if !valid_name || !valid_kind {
+		return None;
+	}
+
+	Some(1 + 1 + end + 1 + 1)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn accepts_known_tokens() {
+		assert_eq!(validate(DEFAULT_FORMAT), Ok(()));
+		assert_eq!(validate("%{Referer}i"), Ok(()));
+		assert_eq!(token_len("%{Referer}i rest"), Some(11));
+	}
+
+	#[test]
+	fn accepts_escaped_percent() {
+		assert_eq!(validate("100%% %s"), Ok(()));
+		assert_eq!(token_len("%%"), Some(2));
+	}
+
+	#[test]
+	fn rejects_trailing_percent() {
+		assert_eq!(validate("%s %"), Err(String::from("%")));
+		assert_eq!(token_len("%"), None);
+	}
+
+	#[test]
+	fn rejects_unknown_letters() {
+		assert_eq!(validate("%s %Z"), Err(String::from("%Z")));
+		assert_eq!(token_len("%Z"), None);
+	}
+
+	#[test]
+	fn accepts_only_the_real_address_variant() {
+		assert_eq!(validate("%{r}a %s"), Ok(()));
+		assert_eq!(validate("%{foo}a %s"), Err(String::from("%{foo}a")));
+	}
+
+	#[test]
+	fn rejects_custom_replacements() {
+		assert_eq!(validate("%{x}xi"), Err(String::from("%{x}x")));
+		assert_eq!(token_len("%{x}xi"), None);
+	}
+}
@@ -0,0 +1,453 @@
+use actix_files::Files;
+use actix_web::body::MessageBody;
+use actix_web::dev::{fn_service, Service, ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::middleware::{ErrorHandlers, Logger};
+use actix_web::{guard, web, App, Error, HttpRequest, HttpResponse};
+use std::path::Path;
+
+use crate::settings::Settings;
+use crate::{
+	access, bom, cache, charset, conditional, dotfiles, errors, fallback, header_size, info,
+	language, maintenance, methods, negotiate, paths, secure_headers, source_maps, stats, throttle,
+};
+
+/// Where the effective configuration is served with `--introspect`.
+pub const INFO_PATH: &str = "/__msaada/info";
+
+/// The app serving the current directory according to the settings, with all
+/// of msaada's middleware.
+pub fn app(
+	settings: &Settings,
+) -> App<
+	impl ServiceFactory<
+		ServiceRequest,
+		Config = (),
+		Response = ServiceResponse<impl MessageBody>,
+		Error = Error,
+		InitError = (),
+	>,
+> {
+	let mut files = file_service(Path::new("./"), settings);
+	// Only registered services inherit the app's empty 404 as default, so
+	// one is needed with overlays too.
+	let has_default = settings.fallback.is_some() || settings.language.is_some();
+	if has_default || !settings.overlays.is_empty() {
+		let fallback = settings.fallback.clone();
+		let language = settings.language.clone();
+		let allowed_dotfiles = settings.allowed_dotfiles.clone();
+		files = files.default_handler(fn_service(move |req: ServiceRequest| {
+			let variant = language
+				.as_deref()
+				.and_then(|default| language::find_variant(&req, default, &allowed_dotfiles));
+			let res = match (variant, &fallback) {
+				(Some((file, language)), _) => language::serve(req, &file, &language),
+				(None, Some(file)) => fallback::serve(req, file),
+				(None, None) => req.into_response(HttpResponse::NotFound().finish()),
+			};
+			async move { Ok(res) }
+		}));
+	}
+	// Overlays are tried first, each falling through to the next.
+	for dir in settings.overlays.iter().rev() {
+		files = file_service(dir, settings).default_handler(files);
+	}
+
+	let index = settings.index_file.clone();
+	let allowed_methods = settings.allowed_methods.clone();
+	let allow = methods::allow_header(allowed_methods.as_deref());
+	let no_cache_html = settings.no_cache_html;
+	let index_redirect = settings.index_redirect;
+	let index_json = settings.index_json;
+	let retry_after = settings.retry_after;
+	// The index file is needed to find the file served for a directory.
+	let strip_bom = settings.strip_bom.then(|| settings.index_file.clone());
+	let throttle_rate = settings.throttle_rate;
+	let allowed_ips = settings.allowed_ips.clone();
+	let denied_ips = settings.denied_ips.clone();
+	let secure_headers = settings.secure_headers;
+	let nosniff = settings.nosniff;
+	let max_header_size = settings.max_header_size;
+	let access_log_format = settings.access_log_format.clone();
+	let default_charset = settings.default_charset.clone();
+	let charsets = settings.charsets.clone();
+
+	let info = settings
+		.introspect
+		.then(|| info::Info::new(&settings.to_json()));
+
+	App::new()
+		.configure(|cfg| {
+			if let Some(info) = info {
+				cfg.route(
+					INFO_PATH,
+					web::route()
+						.guard(guard::Any(guard::Get()).or(guard::Head()))
+						.to(move |req: HttpRequest| {
+							let res = info.respond(&req);
+							async move { res }
+						}),
+				);
+			}
+		})
+		.service(files)
+		.wrap_fn({
+			let allow = allow.clone();
+			move |req, srv| {
+				let res = srv.call(req);
+				let allow = allow.clone();
+				async move { Ok(methods::restrict_to_reads(res.await?, &allow)) }
+			}
+		})
+		.wrap_fn(move |mut req, srv| {
+			let strip = strip_bom
+				.as_deref()
+				.and_then(|index| paths::resolve_file(req.path(), index))
+				.is_some_and(|file| bom::is_stripped(&file));
+			if strip {
+				bom::ignore_range(&mut req);
+			}
+			let default_charset = default_charset.clone();
+			let charsets = charsets.clone();
+			let res = srv.call(req);
+			async move {
+				let mut res = res.await?;
+				if strip {
+					res = bom::strip(res);
+				}
+				if let Some(rate) = throttle_rate {
+					res = throttle::limit(res, rate);
+				}
+				source_maps::set_content_type(&mut res);
+				charset::set_override(&mut res, &charsets);
+				if let Some(charset) = &default_charset {
+					charset::set_default(&mut res, charset);
+				}
+				if no_cache_html {
+					cache::no_cache_html(&mut res);
+				}
+				Ok(res)
+			}
+		})
+		.wrap_fn(move |mut req, srv| {
+			let rejected = max_header_size
+				.and_then(|max| header_size::check(&req, max))
+				.or_else(|| access::check(&req, allowed_ips.as_deref(), &denied_ips))
+				.or_else(|| maintenance::check(retry_after))
+				.or_else(|| paths::check_request_path(&req))
+				.or_else(|| {
+					allowed_methods
+						.as_deref()
+						.and_then(|allowed| methods::check_allowed(&req, allowed, &allow))
+				});
+			let res = match rejected {
+				Some(rejected) => Err(req.into_response(rejected)),
+				None => {
+					conditional::check_if_range(&mut req, &index);
+					Ok(srv.call(req))
+				}
+			};
+			async move {
+				match res {
+					Ok(res) => res.await,
+					Err(rejected) => Ok(rejected),
+				}
+			}
+		})
+		.wrap_fn(move |mut req, srv| {
+			// Directories without a trailing slash are redirected first.
+			let negotiated = index_json
+				&& (!index_redirect || req.path().ends_with('/'))
+				&& negotiate::index_json(&mut req);
+			let res = srv.call(req);
+			async move {
+				let mut res = res.await?;
+				if index_redirect {
+					paths::keep_query(&mut res);
+				}
+				if negotiated {
+					res.headers_mut()
+						.append(header::VARY, HeaderValue::from_static("Accept"));
+				}
+				if secure_headers {
+					secure_headers::apply(&mut res);
+				} else if nosniff {
+					secure_headers::nosniff(&mut res);
+				}
+				Ok(res)
+			}
+		})
+		.wrap(ErrorHandlers::new().default_handler(errors::json_error))
+		.wrap_fn(|req, srv| {
+			let request = stats::track_request();
+			let res = srv.call(req);
+			async move {
+				res.await.map(|res| stats::track(res, request))
+			}
+		})
+		.wrap(Logger::new(&access_log_format).log_target("msaada"))
+}
+
+/// The file service for `dir`, configured according to the settings.
+fn file_service(dir: &Path, settings: &Settings) -> Files {
+	// Hidden paths are filtered below, so `.well-known` can be served.
+	let mut files = Files::new("/", dir)
+		.index_file(settings.index_file.clone())
+		.use_hidden_files()
+		.method_guard(guard::fn_guard(|_| true));
+	if settings.index_redirect {
+		files = files.redirect_to_slash_directory();
+	}
+	let single_file = settings.single_file;
+	let source_maps = settings.source_maps;
+	let allowed_dotfiles = settings.allowed_dotfiles.clone();
+	files.path_filter(move |path, _| {
+		if single_file && !path.as_os_str().is_empty() {
+			return false;
+		}
+		if !dotfiles::is_allowed(path, &allowed_dotfiles) {
+			return false;
+		}
+		source_maps || !source_maps::is_source_map(path)
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use actix_web::http::{Method, StatusCode};
+	use actix_web::test::{self, TestRequest};
+	use std::env;
+
+	/// The defaults of the command line, serving the crate directory tests
+	/// run in.
+	fn settings() -> Settings {
+		Settings {
+			root: env::current_dir().unwrap(),
+			port: 0,
+			url: String::from("http://localhost:0"),
+			index_file: String::from("index.html"),
+			single_file: false,
+			index_redirect: true,
+			source_maps: true,
+			header_timeout: 5,
+			allowed_methods: None,
+			read_only: false,
+			no_cache_html: false,
+			index_json: false,
+			fallback: None,
+			language: None,
+			retry_after: 30,
+			allowed_dotfiles: Vec::new(),
+			strip_bom: false,
+			throttle_rate: None,
+			allowed_ips: None,
+			denied_ips: Vec::new(),
+			idle_timeout: None,
+			secure_headers: false,
+			nosniff: false,
+			default_charset: None,
+			charsets: Vec::new(),
+			overlays: Vec::new(),
+			keep_alive: true,
+			introspect: false,
+			max_header_size: None,
+			access_log_format: String::from(crate::access_log::DEFAULT_FORMAT),
+		}
+	}
+
+	#[actix_web::test]
+	async fn rejects_writes_to_existing_files() {
+		let app = test::init_service(app(&settings())).await;
+
+		for method in [Method::PUT, Method::DELETE] {
+			let req = TestRequest::default()
+				.method(method)
+				.uri("/Cargo.toml")
+				.to_request();
+			let res = test::call_service(&app, req).await;
+			assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+			assert_eq!(res.headers().get(header::ALLOW).unwrap(), "GET, HEAD, OPTIONS");
+		}
+	}
+
+	#[actix_web::test]
+	async fn honours_if_range() {
+		let app = test::init_service(app(&settings())).await;
+		let req = TestRequest::get().uri("/Cargo.toml").to_request();
+		let full = test::call_service(&app, req).await;
+		let etag = full.headers().get(header::ETAG).unwrap().clone();
+		let body = test::read_body(full).await;
+
+		let req = TestRequest::get()
+			.uri("/Cargo.toml")
+			.insert_header((header::RANGE, "bytes=0-9"))
+			.insert_header((header::IF_RANGE, etag))
+			.to_request();
+		let res = test::call_service(&app, req).await;
+		assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+		assert_eq!(test::read_body(res).await, body.slice(0..10));
+
+		let req = TestRequest::get()
+			.uri("/Cargo.toml")
+			.insert_header((header::RANGE, "bytes=0-9"))
+			.insert_header((header::IF_RANGE, "\"stale\""))
+			.to_request();
+		let res = test::call_service(&app, req).await;
+		assert_eq!(res.status(), StatusCode::OK);
+		assert_eq!(test::read_body(res).await, body);
+	}
+
+	#[actix_web::test]
+	async fn serves_the_effective_configuration() {
+		let settings = Settings {
+			introspect: true,
+			..settings()
+		};
+		let app = test::init_service(app(&settings)).await;
+
+		let req = TestRequest::get().uri(INFO_PATH).to_request();
+		let res = test::call_service(&app, req).await;
+		assert_eq!(res.status(), StatusCode::OK);
+		let etag = res.headers().get(header::ETAG).unwrap().clone();
+		let info: serde_json::Value = test::read_body_json(res).await;
+		assert_eq!(info, settings.to_json());
+
+		let req = TestRequest::default()
+			.method(Method::HEAD)
+			.uri(INFO_PATH)
+			.to_request();
+		let res = test::call_service(&app, req).await;
+		assert_eq!(res.status(), StatusCode::OK);
+		assert_eq!(res.headers().get(header::ETAG), Some(&etag));
+
+		let req = TestRequest::get()
+			.uri(INFO_PATH)
+			.insert_header((header::IF_NONE_MATCH, etag))
+			.to_request();
+		let res = test::call_service(&app, req).await;
+		assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+	}
+
+	#[actix_web::test]
+	async fn sets_charsets_per_content_type() {
+		let settings = Settings {
+			charsets: vec![(String::from("text/markdown"), String::from("iso-8859-1"))],
+			..settings()
+		};
+		let app = test::init_service(app(&settings)).await;
+
+		let req = TestRequest::get().uri("/README.md").to_request();
+		let res = test::call_service(&app, req).await;
+		assert_eq!(
+			res.headers().get(header::CONTENT_TYPE).unwrap(),
+			"text/markdown; charset=iso-8859-1"
+		);
+	}
+
+	#[actix_web::test]
+	async fn negotiates_language_variants() {
+		let settings = Settings {
+			language: Some(String::from("en")),
+			..settings()
+		};
+		let app = test::init_service(app(&settings)).await;
+
+		for (accept, language, body) in [
+			("fr-CH, en;q=0.5", "fr", "<p>Bonjour</p>\n"),
+			("de", "en", "<p>Hello</p>\n"),
+		] {
+			let req = TestRequest::get()
+				.uri("/tests/fixtures/i18n/page.html")
+				.insert_header((header::ACCEPT_LANGUAGE, accept))
+				.to_request();
+			let res = test::call_service(&app, req).await;
+			assert_eq!(res.status(), StatusCode::OK);
+			assert_eq!(res.headers().get(header::CONTENT_LANGUAGE).unwrap(), language);
+			assert_eq!(res.headers().get(header::VARY).unwrap(), "Accept-Language");
+			assert_eq!(test::read_body(res).await, body);
+		}
+
+		let req = TestRequest::get()
+			.uri("/tests/fixtures/i18n/plain.html")
+			.insert_header((header::ACCEPT_LANGUAGE, "fr"))
+			.to_request();
+		let res = test::call_service(&app, req).await;
+		assert_eq!(res.status(), StatusCode::OK);
+		assert!(!res.headers().contains_key(header::CONTENT_LANGUAGE));
+	}
+
+	#[actix_web::test]
+	async fn negotiates_index_json() {
+		let settings = Settings {
+			index_json: true,
+			..settings()
+		};
+		let app = test::init_service(app(&settings)).await;
+
+		for (accept, content_type, body) in [
+			("text/html,*/*;q=0.8", "text/html; charset=utf-8", "<p>Users</p>\n"),
+			("application/json", "application/json", "[{\"name\": \"Ada\"}]\n"),
+		] {
+			let req = TestRequest::get()
+				.uri("/tests/fixtures/api/")
+				.insert_header((header::ACCEPT, accept))
+				.to_request();
+			let res = test::call_service(&app, req).await;
+			assert_eq!(res.status(), StatusCode::OK);
+			assert_eq!(res.headers().get(header::CONTENT_TYPE).unwrap(), content_type);
+			assert_eq!(res.headers().get(header::VARY).unwrap(), "Accept");
+			assert_eq!(test::read_body(res).await, body);
+		}
+	}
+
+	#[actix_web::test]
+	async fn keeps_the_query_when_redirecting_directories() {
+		let app = test::init_service(app(&settings())).await;
+
+		let req = TestRequest::get().uri("/tests/fixtures/api?page=2").to_request();
+		let res = test::call_service(&app, req).await;
+		assert_eq!(res.status(), StatusCode::FOUND);
+		assert_eq!(
+			res.headers().get(header::LOCATION).unwrap(),
+			"/tests/fixtures/api/?page=2"
+		);
+	}
+
+	#[actix_web::test]
+	async fn strips_the_bom_and_ignores_ranges() {
+		let settings = Settings {
+			strip_bom: true,
+			..settings()
+		};
+		let app = test::init_service(app(&settings)).await;
+
+		let req = TestRequest::get().uri("/tests/fixtures/bom.txt").to_request();
+		let res = test::call_service(&app, req).await;
+		assert_eq!(res.headers().get(header::ACCEPT_RANGES).unwrap(), "none");
+		assert_eq!(test::read_body(res).await, "hello bom");
+
+		let req = TestRequest::get()
+			.uri("/tests/fixtures/bom.txt")
+			.insert_header((header::RANGE, "bytes=5-"))
+			.to_request();
+		let res = test::call_service(&app, req).await;
+		assert_eq!(res.status(), StatusCode::OK);
+		assert_eq!(test::read_body(res).await, "hello bom");
+	}
+
+	#[actix_web::test]
+	async fn keeps_not_found_for_missing_files() {
+		let app = test::init_service(app(&settings())).await;
+
+		for method in [Method::PUT, Method::DELETE] {
+			let req = TestRequest::default()
+				.method(method)
+				.uri("/missing.html")
+				.to_request();
+			let res = test::call_service(&app, req).await;
+			assert_eq!(res.status(), StatusCode::NOT_FOUND);
+			assert!(!res.headers().contains_key(header::ALLOW));
+		}
+	}
+}
@@ -0,0 +1,150 @@
+use actix_files::file_extension_to_mime;
+use actix_web::body::{BodySize, BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::http::StatusCode;
+use actix_web::web::Bytes;
+use std::error::Error;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Whether `file` is a text file starting with a UTF-8 byte order mark,
+/// whose responses `strip` removes it from.
+pub fn is_stripped(file: &Path) -> bool {
+	let is_text = file
+		.extension()
+		.and_then(|extension| extension.to_str())
+		.is_some_and(|extension| file_extension_to_mime(extension).type_() == "text");
+
+	is_text && starts_with_bom(file)
+}
+
+/// Drops the `Range` header of a request for a file `strip` applies to, as
+/// its byte ranges address the file on disk rather than the stripped body.
+pub fn ignore_range(req: &mut ServiceRequest) {
+	req.headers_mut().remove(header::RANGE);
+}
+
+/// Removes the byte order mark from a full `text/*` response for a file that
+/// `is_stripped`, so browsers don't render it as stray characters. Other
+/// responses are left byte for byte.
+pub fn strip(mut res: ServiceResponse) -> ServiceResponse {
+	let is_text = res
+		.headers()
+		.get(header::CONTENT_TYPE)
+		.and_then(|value| value.to_str().ok())
+		.is_some_and(|value| value.starts_with("text/"));
+
+	if res.status() != StatusCode::OK || !is_text {
+		return res;
+	}
+
+	res.headers_mut()
+		.insert(header::ACCEPT_RANGES, HeaderValue::from_static("none"));
+	res.map_body(|_, body| BoxBody::new(StripBom { body, skip: BOM.len() }))
+}
+
+fn starts_with_bom(file: &Path) -> bool {
+	let mut start = [0; 3];
+	File::open(file)
+		.and_then(|mut file| file.read_exact(&mut start))
+		.is_ok_and(|()| start == BOM)
+}
+
+/// A body with its first `skip` bytes dropped.
+struct StripBom {
+	body: BoxBody,
+	skip: usize,
+}
+
+impl MessageBody for StripBom {
+	type Error = Box<dyn Error>;
+
+	fn size(&self) -> BodySize {
+		match self.body.size() {
+			BodySize::Sized(len) => BodySize::Sized(len.saturating_sub(self.skip as u64)),
+			size => size,
+		}
+	}
+
+	fn poll_next(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+	) -> Poll<Option<Result<Bytes, Self::Error>>> {
+		loop {
+			let chunk = match Pin::new(&mut self.body).poll_next(cx) {
+				Poll::Ready(Some(Ok(chunk))) => chunk,
+				other => return other,
+			};
+			if self.skip == 0 {
+				return Poll::Ready(Some(Ok(chunk)));
+			}
+
+			let skipped = self.skip.min(chunk.len());
+			self.skip -= skipped;
+			if skipped < chunk.len() {
+				return Poll::Ready(Some(Ok(chunk.slice(skipped..))));
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::convert::Infallible;
+
+	/// A body sent in the given chunks.
+	struct Chunks(Vec<&'static [u8]>);
+
+	impl MessageBody for Chunks {
+		type Error = Infallible;
+
+		fn size(&self) -> BodySize {
+			BodySize::Stream
+		}
+
+		fn poll_next(
+			mut self: Pin<&mut Self>,
+			_: &mut Context<'_>,
+		) -> Poll<Option<Result<Bytes, Self::Error>>> {
+			if self.0.is_empty() {
+				return Poll::Ready(None);
+			}
+			Poll::Ready(Some(Ok(Bytes::from_static(self.0.remove(0)))))
+		}
+	}
+
+	async fn stripped(chunks: Vec<&'static [u8]>) -> Bytes {
+		let body = StripBom {
+			body: BoxBody::new(Chunks(chunks)),
+			skip: BOM.len(),
+		};
+		actix_web::body::to_bytes(body).await.unwrap()
+	}
+
+	#[actix_web::test]
+	async fn strips_the_bom_from_the_first_chunk() {
+		assert_eq!(stripped(vec![b"\xEF\xBB\xBFhello", b" bom"]).await, "hello bom");
+	}
+
+	#[actix_web::test]
+	async fn strips_a_bom_split_across_chunks() {
+		let chunks: Vec<&[u8]> = vec![b"\xEF", b"\xBB", b"", b"\xBFhello", b" bom"];
+		assert_eq!(stripped(chunks).await, "hello bom");
+	}
+
+	#[test]
+	fn reports_the_stripped_size() {
+		let body = StripBom {
+			body: BoxBody::new(Bytes::from_static(b"\xEF\xBB\xBFhello")),
+			skip: BOM.len(),
+		};
+		assert_eq!(body.size(), BodySize::Sized(5));
+	}
+}
@@ -0,0 +1,17 @@
+use actix_web::dev::ServiceResponse;
+use actix_web::http::header::{self, HeaderValue};
+
+/// Makes browsers revalidate HTML responses on every load, so edits to pages
+/// show up immediately while other assets keep their default caching.
+pub fn no_cache_html<B>(res: &mut ServiceResponse<B>) {
+	let is_html = res
+		.headers()
+		.get(header::CONTENT_TYPE)
+		.and_then(|value| value.to_str().ok())
+		.is_some_and(|value| value.starts_with("text/html"));
+
+	if is_html && res.status().is_success() {
+		res.headers_mut()
+			.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+	}
+}
@@ -0,0 +1,87 @@
+use actix_web::dev::ServiceResponse;
+use actix_web::http::header::{self, HeaderValue};
+
+/// Adds `charset` to `text/*` responses that don't declare one, such as
+/// `text/xml` and `text/markdown`, so browsers needn't guess the encoding.
+pub fn set_default<B>(res: &mut ServiceResponse<B>, charset: &str) {
+	let content_type = match res
+		.headers()
+		.get(header::CONTENT_TYPE)
+		.and_then(|value| value.to_str().ok())
+	{
+		Some(value) if value.starts_with("text/") && !value.contains("charset=") => value,
+		_ => return,
+	};
+
+	if let Ok(value) = HeaderValue::from_str(&format!("{}; charset={}", content_type, charset)) {
+		res.headers_mut().insert(header::CONTENT_TYPE, value);
+	}
+}
+
+/// Parses a `--charset` override such as `text/plain=iso-8859-1` into the
+/// content type and its charset.
+pub fn parse_override(arg: &str) -> Option<(String, String)> {
+	let (content_type, charset) = arg.split_once('=')?;
+	let content_type = content_type.trim().to_ascii_lowercase();
+	let charset = charset.trim();
+	let is_type = content_type
+		.split_once('/')
+		.is_some_and(|(kind, subtype)| !kind.is_empty() && !subtype.is_empty());
+	if !is_type || !is_valid(charset) {
+		return None;
+	}
+
+	Some((content_type, charset.to_string()))
+}
+
+/// Sets the charset of responses whose content type has one in `overrides`,
+/// replacing whatever charset actix-files chose, e.g. for legacy
+/// `iso-8859-1` pages.
+pub fn set_override<B>(res: &mut ServiceResponse<B>, overrides: &[(String, String)]) {
+	let essence = match res
+		.headers()
+		.get(header::CONTENT_TYPE)
+		.and_then(|value| value.to_str().ok())
+	{
+		Some(value) => value.split(';').next().unwrap_or("").trim().to_ascii_lowercase(),
+		None => return,
+	};
+	let charset = match overrides.iter().find(|(content_type, _)| *content_type == essence) {
+		Some((_, charset)) => charset,
+		None => return,
+	};
+
+	if let Ok(value) = HeaderValue::from_str(&format!("{}; charset={}", essence, charset)) {
+		res.headers_mut().insert(header::CONTENT_TYPE, value);
+	}
+}
+
+/// Whether `name` has the shape of a charset name such as `utf-8` or
+/// `ISO-8859-1`.
+pub fn is_valid(name: &str) -> bool {
+	!name.is_empty()
+		&& name
+			.chars()
+			.all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_overrides() {
+		assert_eq!(
+			parse_override("Text/Plain=iso-8859-1"),
+			Some((String::from("text/plain"), String::from("iso-8859-1")))
+		);
+	}
+
+	#[test]
+	fn rejects_malformed_overrides() {
+		assert_eq!(parse_override("text/plain"), None);
+		assert_eq!(parse_override("text=utf-8"), None);
+		assert_eq!(parse_override("text/plain="), None);
+		assert_eq!(parse_override("text/plain=utf 8"), None);
+	}
+}
@@ -0,0 +1,54 @@
+use actix_files::NamedFile;
+use actix_web::dev::ServiceRequest;
+use actix_web::http::header::{self, EntityTag, Header, HttpDate, IfRange};
+
+use crate::paths;
+
+/// Drops the `Range` header of a request whose `If-Range` validator no longer
+/// matches the file on disk, so the client receives the full file with a 200
+/// instead of a part of a representation it doesn't have.
+///
+/// actix-files honours `Range` but ignores `If-Range`, so this runs before the
+/// request reaches the `Files` service.
+pub fn check_if_range(req: &mut ServiceRequest, index_file: &str) {
+	if !req.headers().contains_key(header::RANGE) || !req.headers().contains_key(header::IF_RANGE) {
+		return;
+	}
+
+	let path = match paths::resolve_file(req.path(), index_file) {
+		Some(path) => path,
+		None => return,
+	};
+
+	// Let actix-files compute the validators so they are exactly the ones the
+	// response will carry. The body is streamed lazily and never read here.
+	let current = match NamedFile::open(&path) {
+		Ok(file) => file.into_response(req.request()),
+		Err(_) => return,
+	};
+	let current_header = |name| {
+		current
+			.headers()
+			.get(name)
+			.and_then(|value| value.to_str().ok())
+			.map(str::to_owned)
+	};
+
+	let fresh = match IfRange::parse(req) {
+		Ok(IfRange::EntityTag(tag)) => current_header(header::ETAG)
+			.and_then(|etag| etag.parse::<EntityTag>().ok())
+			.is_some_and(|etag| etag.strong_eq(&tag)),
+		Ok(IfRange::Date(date)) => current_header(header::LAST_MODIFIED)
+			.and_then(|modified| modified.parse::<HttpDate>().ok())
+			.is_some_and(|modified| modified == date),
+		Err(_) => false,
+	};
+
+	if !fresh {
+		log::debug!(
+			"If-Range validator is stale for {}, ignoring Range",
+			req.path()
+		);
+		req.headers_mut().remove(header::RANGE);
+	}
+}
@@ -0,0 +1,57 @@
+use std::path::{Path, PathBuf};
+
+use crate::list;
+use crate::paths;
+
+/// Hidden paths that are always served, for ACME challenges and app
+/// association files.
+pub const ALWAYS_ALLOWED: &str = ".well-known";
+
+/// Parses a comma separated list of paths such as `.config,/.env.example`,
+/// relative to the served directory.
+pub fn parse_list(list: &str) -> Result<Vec<PathBuf>, String> {
+	list::parse(list, |path| {
+		paths::normalize_request_path(path).filter(|path| !path.as_os_str().is_empty())
+	})
+}
+
+/// Whether `path` may be served: it has no segment starting with a dot, or it
+/// is `.well-known` or one of the `allowed` paths, or inside them.
+pub fn is_allowed(path: &Path, allowed: &[PathBuf]) -> bool {
+	let hidden = path
+		.components()
+		.any(|segment| segment.as_os_str().to_string_lossy().starts_with('.'));
+
+	!hidden
+		|| path.starts_with(ALWAYS_ALLOWED)
+		|| allowed.iter().any(|prefix| path.starts_with(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn serves_well_known_but_not_other_dotfiles() {
+		assert!(is_allowed(Path::new(".well-known/test"), &[]));
+		assert!(!is_allowed(Path::new(".env"), &[]));
+		assert!(!is_allowed(Path::new("app/.git/config"), &[]));
+		assert!(is_allowed(Path::new("app/index.html"), &[]));
+	}
+
+	#[test]
+	fn serves_allowed_dotfiles() {
+		let allowed = parse_list(".env.example, /.config").unwrap();
+		assert_eq!(allowed, [PathBuf::from(".env.example"), PathBuf::from(".config")]);
+
+		assert!(is_allowed(Path::new(".env.example"), &allowed));
+		assert!(is_allowed(Path::new(".config/settings.json"), &allowed));
+		assert!(!is_allowed(Path::new(".env"), &allowed));
+	}
+
+	#[test]
+	fn rejects_paths_outside_the_served_directory() {
+		assert_eq!(parse_list("../.env"), Err(String::from("../.env")));
+		assert_eq!(parse_list("/"), Err(String::from("/")));
+	}
+}
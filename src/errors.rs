@@ -0,0 +1,41 @@
+use actix_web::body::{BoxBody, EitherBody};
+use actix_web::dev::ServiceResponse;
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::middleware::ErrorHandlerResponse;
+use actix_web::Result;
+use serde_json::json;
+
+use crate::negotiate;
+
+/// Replaces the body of an error response with a JSON envelope when the
+/// client prefers JSON:
+///
+/// ```json
+/// {"error": {"status": 404, "message": "Not Found"}}
+/// ```
+///
+/// Other clients get the response unchanged.
+pub fn json_error<B>(res: ServiceResponse<B>) -> Result<ErrorHandlerResponse<B>> {
+	if !negotiate::prefers_json(res.request()) {
+		return Ok(ErrorHandlerResponse::Response(res.map_into_left_body()));
+	}
+
+	let status = res.status();
+	let body = json!({
+		"error": {
+			"status": status.as_u16(),
+			"message": status.canonical_reason().unwrap_or("Unknown Error"),
+		}
+	})
+	.to_string();
+
+	let res = res.map_body(|head, _| {
+		head.headers_mut().insert(
+			header::CONTENT_TYPE,
+			HeaderValue::from_static("application/json"),
+		);
+		EitherBody::right(BoxBody::new(body))
+	});
+
+	Ok(ErrorHandlerResponse::Response(res))
+}
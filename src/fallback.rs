@@ -0,0 +1,42 @@
+use actix_files::NamedFile;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::Method;
+use actix_web::HttpResponse;
+use std::path::{Path, PathBuf};
+
+use crate::paths;
+use crate::source_maps;
+
+/// Resolves the `--fallback` argument, given relative to the served directory
+/// with or without a leading slash, to a file in it.
+pub fn resolve(arg: &str) -> Option<PathBuf> {
+	let path = Path::new(".").join(paths::normalize_request_path(arg)?);
+	if path.is_file() {
+		Some(path)
+	} else {
+		None
+	}
+}
+
+/// Answers a `GET` or `HEAD` request that matched no file with the fallback
+/// file and a `200`. Other methods and source maps, which devtools should not
+/// receive a page for, keep their `404`.
+pub fn serve(req: ServiceRequest, file: &Path) -> ServiceResponse {
+	let method = req.method();
+	let is_read = method == Method::GET || method == Method::HEAD;
+	if !is_read || source_maps::is_source_map(Path::new(req.path())) {
+		return req.into_response(HttpResponse::NotFound().finish());
+	}
+
+	match NamedFile::open(file) {
+		Ok(file) => {
+			let (req, _) = req.into_parts();
+			let res = file.into_response(&req);
+			ServiceResponse::new(req, res)
+		}
+		Err(err) => {
+			log::error!("unable to open fallback {}: {}", file.display(), err);
+			req.into_response(HttpResponse::NotFound().finish())
+		}
+	}
+}
@@ -0,0 +1,33 @@
+use actix_web::dev::ServiceRequest;
+use actix_web::HttpResponse;
+
+/// The size of a request head past which actix-http answers with a `431`
+/// itself, before the request reaches `check`.
+pub const BUILTIN_LIMIT: usize = 128 * 1024;
+
+/// The `431` a request whose request line and headers together exceed `max`
+/// bytes is rejected with, as a reverse proxy with that limit would. Only
+/// limits below `BUILTIN_LIMIT` have an effect.
+pub fn check(req: &ServiceRequest, max: usize) -> Option<HttpResponse> {
+	let size = head_size(req);
+	if size <= max {
+		return None;
+	}
+
+	log::debug!("rejecting request head of {} bytes", size);
+	Some(HttpResponse::RequestHeaderFieldsTooLarge().finish())
+}
+
+/// The size of the request head as sent, e.g. `GET / HTTP/1.1\r\n` followed
+/// by a `name: value\r\n` line per header.
+fn head_size(req: &ServiceRequest) -> usize {
+	let version = "HTTP/1.1".len();
+	let line = req.method().as_str().len() + 1 + req.uri().to_string().len() + 1 + version + 2;
+	let headers: usize = req
+		.headers()
+		.iter()
+		.map(|(name, value)| name.as_str().len() + 2 + value.len() + 2)
+		.sum();
+
+	line + headers
+}
@@ -0,0 +1,42 @@
+use actix_web::http::header::{self, ContentType, EntityTag, Header, IfNoneMatch};
+use actix_web::{HttpRequest, HttpResponse};
+use serde_json::Value;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// The effective configuration served with `--introspect`, along with an
+/// ETag hashed from its content so clients can revalidate it.
+#[derive(Clone)]
+pub struct Info {
+	body: String,
+	etag: EntityTag,
+}
+
+impl Info {
+	pub fn new(info: &Value) -> Self {
+		let body = info.to_string();
+		let mut hasher = DefaultHasher::new();
+		body.hash(&mut hasher);
+		let etag = EntityTag::new_strong(format!("{:016x}", hasher.finish()));
+
+		Info { body, etag }
+	}
+
+	/// The JSON with its ETag, or a `304` when `If-None-Match` has it.
+	pub fn respond(&self, req: &HttpRequest) -> HttpResponse {
+		let fresh = match IfNoneMatch::parse(req) {
+			Ok(IfNoneMatch::Any) => true,
+			Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(&self.etag)),
+			Err(_) => false,
+		};
+		if fresh {
+			return HttpResponse::NotModified()
+				.insert_header(header::ETag(self.etag.clone()))
+				.finish();
+		}
+
+		HttpResponse::Ok()
+			.insert_header(ContentType::json())
+			.insert_header(header::ETag(self.etag.clone()))
+			.body(self.body.clone())
+	}
+}
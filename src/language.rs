@@ -0,0 +1,111 @@
+use actix_files::NamedFile;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, AcceptLanguage, Header, HeaderValue, Preference};
+use actix_web::http::Method;
+use actix_web::{HttpMessage, HttpResponse};
+use std::path::{Path, PathBuf};
+
+use crate::dotfiles;
+use crate::paths;
+use crate::source_maps;
+
+/// Whether `tag` has the shape of a language tag such as `en` or `pt-BR`, so
+/// it can be part of a file name.
+pub fn is_valid(tag: &str) -> bool {
+	!tag.is_empty() && tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// The languages to look for, by preference: those in `Accept-Language`,
+/// each followed by its primary language, and finally `default`.
+fn candidates<M: HttpMessage>(msg: &M, default: &str) -> Vec<String> {
+	let accepted = AcceptLanguage::parse(msg)
+		.map(|accept| accept.ranked())
+		.unwrap_or_default();
+	let tags = accepted
+		.iter()
+		.filter_map(Preference::item)
+		.flat_map(|tag| [tag.to_string(), tag.primary_language().to_string()])
+		.chain([default.to_string()]);
+
+	let mut languages = Vec::new();
+	for tag in tags {
+		if is_valid(&tag) && !languages.contains(&tag) {
+			languages.push(tag);
+		}
+	}
+	languages
+}
+
+/// For a `GET` or `HEAD` request for a file that doesn't exist, such as
+/// `/page.html`, finds the variant in the client's preferred language, such
+/// as `page.fr.html`, falling back to the `default` language. Returns the
+/// file and its language.
+pub fn find_variant(
+	req: &ServiceRequest,
+	default: &str,
+	allowed_dotfiles: &[PathBuf],
+) -> Option<(PathBuf, String)> {
+	if req.method() != Method::GET && req.method() != Method::HEAD {
+		return None;
+	}
+	let path = paths::normalize_request_path(req.path())?;
+	if source_maps::is_source_map(&path) {
+		return None;
+	}
+	let stem = path.file_stem()?.to_str()?;
+	let extension = path.extension()?.to_str()?;
+
+	candidates(req, default).into_iter().find_map(|language| {
+		let variant = path.with_file_name(format!("{}.{}.{}", stem, language, extension));
+		let file = paths::locate(&variant);
+		let servable = dotfiles::is_allowed(&variant, allowed_dotfiles) && file.is_file();
+		servable.then_some((file, language))
+	})
+}
+
+/// Serves a language variant found by `find_variant`, telling caches that the
+/// response depends on `Accept-Language`.
+pub fn serve(req: ServiceRequest, file: &Path, language: &str) -> ServiceResponse {
+	let (req, _) = req.into_parts();
+	let mut res = match NamedFile::open(file) {
+		Ok(file) => file.into_response(&req),
+		Err(err) => {
+			log::error!("unable to open {}: {}", file.display(), err);
+			return ServiceResponse::new(req, HttpResponse::NotFound().finish());
+		}
+	};
+	if let Ok(value) = HeaderValue::from_str(language) {
+		res.headers_mut().insert(header::CONTENT_LANGUAGE, value);
+	}
+	res.headers_mut()
+		.append(header::VARY, HeaderValue::from_static("Accept-Language"));
+
+	ServiceResponse::new(req, res)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use actix_web::test::TestRequest;
+
+	#[test]
+	fn ranks_accepted_languages_before_the_default() {
+		let req = TestRequest::default()
+			.insert_header((header::ACCEPT_LANGUAGE, "fr-CH, de;q=0.5, fr;q=0.8"))
+			.to_http_request();
+		assert_eq!(candidates(&req, "en"), ["fr-CH", "fr", "de", "en"]);
+	}
+
+	#[test]
+	fn falls_back_to_the_default() {
+		let req = TestRequest::default().to_http_request();
+		assert_eq!(candidates(&req, "en"), ["en"]);
+	}
+
+	#[test]
+	fn validates_tags() {
+		assert!(is_valid("pt-BR"));
+		assert!(!is_valid(""));
+		assert!(!is_valid("../en"));
+	}
+}
@@ -0,0 +1,9 @@
+/// Parses a comma separated list with `parse`, skipping empty entries. The
+/// error is the first entry `parse` rejects.
+pub fn parse<T>(list: &str, parse: impl Fn(&str) -> Option<T>) -> Result<Vec<T>, String> {
+	list.split(',')
+		.map(str::trim)
+		.filter(|entry| !entry.is_empty())
+		.map(|entry| parse(entry).ok_or_else(|| entry.to_string()))
+		.collect()
+}
@@ -0,0 +1,71 @@
+use std::env;
+use std::io;
+use std::net::TcpListener;
+use std::process;
+
+/// The first file descriptor passed by socket activation, `SD_LISTEN_FDS_START`.
+const LISTEN_FDS_START: i32 = 3;
+
+/// The file descriptor of a socket passed following systemd's `LISTEN_FDS`
+/// convention, if this process was given one.
+pub fn fd_from_env() -> Option<i32> {
+	let pid = env::var("LISTEN_PID").ok()?.parse::<u32>().ok()?;
+	let fds = env::var("LISTEN_FDS").ok()?.parse::<u32>().ok()?;
+	if pid != process::id() || fds == 0 {
+		return None;
+	}
+
+	Some(LISTEN_FDS_START)
+}
+
+/// Takes over an already bound TCP socket, e.g. from a process manager,
+/// instead of binding one.
+#[cfg(unix)]
+pub fn inherit(fd: i32) -> io::Result<TcpListener> {
+	use std::os::unix::io::FromRawFd;
+
+	if !is_listening(fd)? {
+		return Err(io::Error::new(
+			io::ErrorKind::InvalidInput,
+			"not a listening socket",
+		));
+	}
+
+	// SAFETY: `fd` is a listening socket, as checked above. The runtime opens
+	// no listening sockets of its own before this, so it is the one the parent
+	// process handed over, and this listener becomes its only owner.
+	let listener = unsafe { TcpListener::from_raw_fd(fd) };
+	// Fails for sockets other than TCP ones.
+	listener.local_addr()?;
+
+	Ok(listener)
+}
+
+#[cfg(unix)]
+fn is_listening(fd: i32) -> io::Result<bool> {
+	let mut accepting: libc::c_int = 0;
+	let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+	// SAFETY: the option value and length point to a live, matching c_int.
+	let res = unsafe {
+		libc::getsockopt(
+			fd,
+			libc::SOL_SOCKET,
+			libc::SO_ACCEPTCONN,
+			&mut accepting as *mut libc::c_int as *mut libc::c_void,
+			&mut len,
+		)
+	};
+	if res != 0 {
+		return Err(io::Error::last_os_error());
+	}
+
+	Ok(accepting != 0)
+}
+
+#[cfg(not(unix))]
+pub fn inherit(_fd: i32) -> io::Result<TcpListener> {
+	Err(io::Error::new(
+		io::ErrorKind::Unsupported,
+		"inheriting sockets is only supported on unix",
+	))
+}
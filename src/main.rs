@@ -1,10 +1,41 @@
-use actix_files::Files;
-use actix_web::{middleware::Logger, App, HttpServer};
+use actix_web::http::KeepAlive;
+use actix_web::HttpServer;
 use clap::Arg;
+use clap::ArgAction;
 use clap::Command;
+use settings::Settings;
 use std::env;
-use std::path::Path;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::exit;
+use std::str::FromStr;
+use std::time::Duration;
+
+mod access;
+mod access_log;
+mod app;
+mod bom;
+mod cache;
+mod charset;
+mod conditional;
+mod dotfiles;
+mod errors;
+mod fallback;
+mod header_size;
+mod info;
+mod language;
+mod list;
+mod listen;
+mod maintenance;
+mod methods;
+mod negotiate;
+mod paths;
+mod secure_headers;
+mod settings;
+mod shutdown;
+mod source_maps;
+mod stats;
+mod throttle;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -16,7 +47,6 @@ async fn main() -> std::io::Result<()> {
 			Arg::new("port")
 				.short('p')
 				.long("port")
-				.required(true)
 				.help("The port number to use"),
 		)
 		.arg(
@@ -24,35 +54,523 @@ async fn main() -> std::io::Result<()> {
 				.short('d')
 				.long("dir")
 				.required(true)
-				.help("The directory to serve from"),
+				.help("The directory to serve from, or a single file to serve at /"),
+		)
+		.arg(
+			Arg::new("dry-run")
+				.long("dry-run")
+				.action(ArgAction::SetTrue)
+				.help("Validate the arguments, print what would be served and exit"),
+		)
+		.arg(
+			Arg::new("no-index-redirect")
+				.long("no-index-redirect")
+				.action(ArgAction::SetTrue)
+				.help("Serve a directory's index.html without redirecting to a trailing slash"),
+		)
+		.arg(
+			Arg::new("no-source-maps")
+				.long("no-source-maps")
+				.action(ArgAction::SetTrue)
+				.help("Respond with 404 to requests for .map files"),
+		)
+		.arg(
+			Arg::new("public-url")
+				.long("public-url")
+				.help("The URL to advertise at startup, e.g. when behind port mapping; does not affect binding"),
+		)
+		.arg(
+			Arg::new("header-timeout")
+				.long("header-timeout")
+				.default_value("5")
+				.help("Seconds a client has to send the request headers before a 408 is returned, 0 to disable"),
+		)
+		.arg(
+			Arg::new("show-config")
+				.long("show-config")
+				.action(ArgAction::SetTrue)
+				.help("Print the effective configuration at startup"),
+		)
+		.arg(
+			Arg::new("allow-methods")
+				.long("allow-methods")
+				.help("Comma separated list of allowed request methods, e.g. GET,HEAD; others get a 405"),
+		)
+		.arg(
+			Arg::new("read-only")
+				.long("read-only")
+				.action(ArgAction::SetTrue)
+				.help("Only accept GET, HEAD and OPTIONS requests; others get a 405"),
+		)
+		.arg(
+			Arg::new("no-cache-html")
+				.long("no-cache-html")
+				.action(ArgAction::SetTrue)
+				.help("Send Cache-Control: no-cache with HTML responses"),
+		)
+		.arg(
+			Arg::new("index-json")
+				.long("index-json")
+				.action(ArgAction::SetTrue)
+				.help("Serve a directory's index.json instead of index.html to clients that prefer JSON"),
+		)
+		.arg(
+			Arg::new("fallback")
+				.long("fallback")
+				.help("A file in the served directory to answer GET requests for missing paths with, e.g. /maintenance.html"),
+		)
+		.arg(
+			Arg::new("language")
+				.long("language")
+				.help("Serve page.LANG.html for a missing page.html by Accept-Language, falling back to this language, e.g. en"),
+		)
+		.arg(
+			Arg::new("maintenance")
+				.long("maintenance")
+				.action(ArgAction::SetTrue)
+				.help("Answer every request with a 503 and the served directory's 503.html, if any; SIGUSR2 toggles this at runtime"),
+		)
+		.arg(
+			Arg::new("retry-after")
+				.long("retry-after")
+				.default_value("30")
+				.help("Seconds sent in the Retry-After header of maintenance responses"),
+		)
+		.arg(
+			Arg::new("listen-fd")
+				.long("listen-fd")
+				.help("Serve on an already bound socket passed in as this file descriptor instead of binding to --port"),
+		)
+		.arg(
+			Arg::new("allow-dotfiles")
+				.long("allow-dotfiles")
+				.help("Comma separated list of hidden paths to serve, e.g. .env.example; .well-known is always served"),
+		)
+		.arg(
+			Arg::new("strip-bom")
+				.long("strip-bom")
+				.action(ArgAction::SetTrue)
+				.help("Remove a leading UTF-8 byte order mark from text files when serving them"),
+		)
+		.arg(
+			Arg::new("throttle")
+				.long("throttle")
+				.help("Limit the bandwidth of each response to simulate a slow connection, e.g. 1mbps or 512kbps"),
+		)
+		.arg(
+			Arg::new("allow-ip")
+				.long("allow-ip")
+				.help("Comma separated list of client addresses or ranges to serve, e.g. 192.168.1.0/24; others get a 403"),
+		)
+		.arg(
+			Arg::new("deny-ip")
+				.long("deny-ip")
+				.help("Comma separated list of client addresses or ranges to answer with a 403, taking precedence over --allow-ip"),
+		)
+		.arg(
+			Arg::new("shutdown-on-idle")
+				.long("shutdown-on-idle")
+				.help("Stop the server after this many seconds without requests"),
+		)
+		.arg(
+			Arg::new("secure-headers")
+				.long("secure-headers")
+				.action(ArgAction::SetTrue)
+				.help("Send X-Content-Type-Options, X-Frame-Options and Referrer-Policy headers as a hardened server would"),
+		)
+		.arg(
+			Arg::new("overlay")
+				.long("overlay")
+				.action(ArgAction::Append)
+				.help("A directory to serve files from before the served directory, e.g. theme overrides; repeat to stack several"),
+		)
+		.arg(
+			Arg::new("banner-file")
+				.long("banner-file")
+				.help("A file whose contents are printed at startup, e.g. a notice for the team"),
+		)
+		.arg(
+			Arg::new("no-keep-alive")
+				.long("no-keep-alive")
+				.action(ArgAction::SetTrue)
+				.help("Close the connection after every response, as HTTP/1.0 clients expect"),
+		)
+		.arg(
+			Arg::new("introspect")
+				.long("introspect")
+				.action(ArgAction::SetTrue)
+				.help("Serve the effective configuration as JSON at /__msaada/info"),
+		)
+		.arg(
+			Arg::new("nosniff")
+				.long("nosniff")
+				.action(ArgAction::SetTrue)
+				.help("Send X-Content-Type-Options: nosniff, also part of --secure-headers"),
+		)
+		.arg(
+			Arg::new("default-charset")
+				.long("default-charset")
+				.help("Charset added to text responses that declare none; utf-8 with --nosniff or --secure-headers"),
+		)
+		.arg(
+			Arg::new("charset")
+				.long("charset")
+				.action(ArgAction::Append)
+				.help("The charset of a content type, e.g. text/plain=iso-8859-1, replacing the one it is served with; repeat for several types"),
+		)
+		.arg(
+			Arg::new("max-header-size")
+				.long("max-header-size")
+				.help("Answer requests whose request line and headers exceed this many bytes with a 431, below 131072"),
+		)
+		.arg(
+			Arg::new("access-log-format")
+				.long("access-log-format")
+				.help("The access log line, using actix-web's Logger tokens such as %a \"%r\" %s %b %D"),
 		)
 		.get_matches();
 
-	let port_arg = matches.get_one::<String>("port").unwrap();
-	let port = port_arg.parse::<u16>().unwrap();
+	// Inherited before anything else is opened, see `listen::inherit`.
+	let listen_fd = match matches.get_one::<String>("listen-fd") {
+		Some(_) => Some(parse_arg::<i32>(&matches, "listen-fd")),
+		None => listen::fd_from_env(),
+	};
+	let listener = listen_fd.map(|fd| {
+		listen::inherit(fd).unwrap_or_else(|err| {
+			eprintln!("Invalid listen fd {}: {}", fd, err);
+			exit(shutdown::EXIT_USAGE)
+		})
+	});
+
+	let port: u16 = match &listener {
+		Some(listener) => listener.local_addr()?.port(),
+		None if matches.contains_id("port") => parse_arg(&matches, "port"),
+		None => {
+			eprintln!("Missing --port, or a socket passed with --listen-fd or LISTEN_FDS");
+			exit(shutdown::EXIT_USAGE)
+		}
+	};
+	let header_timeout: u64 = parse_arg(&matches, "header-timeout");
+	let retry_after: u64 = parse_arg(&matches, "retry-after");
+	let max_header_size: Option<usize> = matches
+		.contains_id("max-header-size")
+		.then(|| parse_arg(&matches, "max-header-size"));
+	if max_header_size.is_some_and(|max| max >= header_size::BUILTIN_LIMIT) {
+		eprintln!(
+			"Invalid max-header-size: must be below {}, where actix-web answers with a 431 itself",
+			header_size::BUILTIN_LIMIT
+		);
+		exit(shutdown::EXIT_USAGE)
+	}
+	let idle_timeout: Option<u64> = matches
+		.contains_id("shutdown-on-idle")
+		.then(|| parse_arg(&matches, "shutdown-on-idle"));
 
 	let dir_arg = matches.get_one::<String>("directory").unwrap();
-	let dir = Path::new(&dir_arg);
+	let mut dir = Path::new(&dir_arg);
+
+	// A file is served on its own at `/` from its parent directory.
+	let mut single_file = None;
+	if dir.is_file() {
+		single_file = dir
+			.file_name()
+			.map(|name| name.to_string_lossy().into_owned());
+		dir = match dir.parent() {
+			Some(parent) if !parent.as_os_str().is_empty() => parent,
+			_ => Path::new("."),
+		};
+	}
+
+	// Read relative to the launch directory, before it changes.
+	let banner = matches.get_one::<String>("banner-file").map(|file| {
+		fs::read_to_string(file).unwrap_or_else(|err| {
+			eprintln!("Unreadable banner file {}: {}", file, err);
+			exit(shutdown::EXIT_USAGE)
+		})
+	});
+
+	// Resolved against the launch directory, before it changes.
+	let overlays: Vec<PathBuf> = matches
+		.get_many::<String>("overlay")
+		.unwrap_or_default()
+		.map(|overlay| match Path::new(overlay).canonicalize() {
+			Ok(path) if path.is_dir() => path,
+			_ => {
+				eprintln!("Unknown overlay: {}", overlay);
+				exit(shutdown::EXIT_USAGE)
+			}
+		})
+		.collect();
+
 	let is_path_set = env::set_current_dir(dir);
 
 	match is_path_set {
 		Ok(()) => (),
 		Err(_) => {
-			println!("Unknown path: {}", dir_arg);
-			exit(1)
+			eprintln!("Unknown path: {}", dir_arg);
+			exit(shutdown::EXIT_USAGE)
 		}
 	}
 
 	env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
 
-	log::info!("starting HTTP server at http://localhost:{0}", port_arg);
+	let url = match matches.get_one::<String>("public-url") {
+		Some(public_url) => public_url.clone(),
+		None => format!("http://localhost:{}", port),
+	};
+
+	let mut allowed_methods = matches.get_one::<String>("allow-methods").map(|list| {
+		match methods::parse_list(list) {
+			Ok(allowed) if allowed.is_empty() => {
+				eprintln!("--allow-methods needs at least one method");
+				exit(shutdown::EXIT_USAGE)
+			}
+			Ok(allowed) => allowed,
+			Err(method) => {
+				eprintln!("Invalid method in --allow-methods: {}", method);
+				exit(shutdown::EXIT_USAGE)
+			}
+		}
+	});
+
+	let read_only = matches.get_flag("read-only");
+	if read_only {
+		let allowed: Vec<_> = match allowed_methods {
+			Some(allowed) => allowed
+				.into_iter()
+				.filter(|method| methods::READ_ONLY.contains(method))
+				.collect(),
+			None => methods::READ_ONLY.to_vec(),
+		};
+		if allowed.is_empty() {
+			eprintln!("--read-only leaves none of the methods in --allow-methods");
+			exit(shutdown::EXIT_USAGE)
+		}
+		allowed_methods = Some(allowed);
+	}
+
+	let fallback = match matches.get_one::<String>("fallback") {
+		Some(_) if single_file.is_some() => {
+			eprintln!("--fallback has no effect when serving a single file");
+			exit(shutdown::EXIT_USAGE)
+		}
+		Some(file) => Some(fallback::resolve(file).unwrap_or_else(|| {
+			eprintln!("Invalid --fallback, not a file in the served directory: {}", file);
+			exit(shutdown::EXIT_USAGE)
+		})),
+		None => None,
+	};
+
+	let index_json = matches.get_flag("index-json");
+	if index_json && single_file.is_some() {
+		eprintln!("--index-json has no effect when serving a single file");
+		exit(shutdown::EXIT_USAGE)
+	}
+
+	let language = match matches.get_one::<String>("language") {
+		Some(_) if single_file.is_some() => {
+			eprintln!("--language has no effect when serving a single file");
+			exit(shutdown::EXIT_USAGE)
+		}
+		Some(tag) if !language::is_valid(tag) => {
+			eprintln!("Invalid --language: {}", tag);
+			exit(shutdown::EXIT_USAGE)
+		}
+		language => language.cloned(),
+	};
+
+	let allowed_dotfiles = match matches.get_one::<String>("allow-dotfiles") {
+		Some(list) => dotfiles::parse_list(list).unwrap_or_else(|path| {
+			eprintln!("Invalid path in --allow-dotfiles: {}", path);
+			exit(shutdown::EXIT_USAGE)
+		}),
+		None => Vec::new(),
+	};
+
+	let throttle_rate = matches.get_one::<String>("throttle").map(|rate| {
+		throttle::parse_rate(rate).unwrap_or_else(|| {
+			eprintln!("Invalid --throttle, expected a rate such as 1mbps: {}", rate);
+			exit(shutdown::EXIT_USAGE)
+		})
+	});
+
+	let parse_ips = |name| {
+		matches.get_one::<String>(name).map(|list| match access::parse_list(list) {
+			Ok(cidrs) if cidrs.is_empty() => {
+				eprintln!("--{} needs at least one address", name);
+				exit(shutdown::EXIT_USAGE)
+			}
+			Ok(cidrs) => cidrs,
+			Err(cidr) => {
+				eprintln!("Invalid address in --{}: {}", name, cidr);
+				exit(shutdown::EXIT_USAGE)
+			}
+		})
+	};
+	let allowed_ips = parse_ips("allow-ip");
+	let denied_ips = parse_ips("deny-ip").unwrap_or_default();
+
+	let secure_headers = matches.get_flag("secure-headers");
+	let nosniff = matches.get_flag("nosniff") || secure_headers;
+	let default_charset = match matches.get_one::<String>("default-charset") {
+		Some(name) if !charset::is_valid(name) => {
+			eprintln!("Invalid --default-charset: {}", name);
+			exit(shutdown::EXIT_USAGE)
+		}
+		Some(name) => Some(name.clone()),
+		None => nosniff.then(|| String::from("utf-8")),
+	};
+
+	let charsets = matches
+		.get_many::<String>("charset")
+		.unwrap_or_default()
+		.map(|arg| {
+			charset::parse_override(arg).unwrap_or_else(|| {
+				eprintln!("Invalid --charset, expected a pair such as text/plain=iso-8859-1: {}", arg);
+				exit(shutdown::EXIT_USAGE)
+			})
+		})
+		.collect();
+
+	let access_log_format = match matches.get_one::<String>("access-log-format") {
+		Some(format) => {
+			if let Err(token) = access_log::validate(format) {
+				eprintln!("Unknown token in --access-log-format: {}", token);
+				exit(shutdown::EXIT_USAGE)
+			}
+			format.clone()
+		}
+		None => String::from(access_log::DEFAULT_FORMAT),
+	};
+
+	let mut root = env::current_dir()?;
+	if let Some(file) = &single_file {
+		root.push(file);
+	}
+
+	let settings = Settings {
+		root,
+		port,
+		url,
+		single_file: single_file.is_some(),
+		index_file: single_file
+			.clone()
+			.unwrap_or_else(|| String::from("index.html")),
+		index_redirect: !matches.get_flag("no-index-redirect"),
+		source_maps: !matches.get_flag("no-source-maps"),
+		header_timeout,
+		allowed_methods,
+		read_only,
+		no_cache_html: matches.get_flag("no-cache-html"),
+		index_json,
+		fallback,
+		language,
+		retry_after,
+		allowed_dotfiles,
+		strip_bom: matches.get_flag("strip-bom"),
+		throttle_rate,
+		allowed_ips,
+		denied_ips,
+		idle_timeout,
+		secure_headers,
+		nosniff,
+		default_charset,
+		charsets,
+		keep_alive: !matches.get_flag("no-keep-alive"),
+		introspect: matches.get_flag("introspect"),
+		max_header_size,
+		access_log_format,
+		overlays,
+	};
+
+	if matches.get_flag("show-config") {
+		settings.log();
+	}
+
+	if matches.get_flag("dry-run") {
+		log::info!(
+			"dry run: would serve {} at {}",
+			settings.root.display(),
+			settings.url
+		);
+		exit(shutdown::EXIT_SUCCESS)
+	}
+
+	log::info!("starting HTTP server at {}", settings.url);
+	for line in banner.iter().flat_map(|banner| banner.lines()) {
+		log::info!("{}", line);
+	}
+	if settings.read_only {
+		log::info!("read-only mode: only GET, HEAD and OPTIONS requests are accepted");
+	}
+	if let Some(max) = settings.max_header_size {
+		log::info!("request heads over {} bytes get a 431", max);
+	}
+	if !settings.keep_alive {
+		log::info!("keep-alive disabled: every connection closes after one response");
+	}
+	// Set before maintenance mode reads its page through them.
+	paths::set_overlays(settings.overlays.clone());
+	if matches.get_flag("maintenance") {
+		maintenance::enable();
+		log::info!("maintenance mode: every request gets a 503");
+	}
+
+	stats::start();
+	shutdown::setup_stats_signal(settings.clone());
+	shutdown::setup_maintenance_signal();
 
-	HttpServer::new(|| {
-		App::new()
-			.service(Files::new("/", "./").index_file("index.html"))
-			.wrap(Logger::default().log_target("msaada"))
-	})
-	.bind(("127.0.0.1", port))?
-	.run()
-	.await
+	let keep_alive = if settings.keep_alive {
+		KeepAlive::default()
+	} else {
+		KeepAlive::Disabled
+	};
+
+	let server = HttpServer::new(move || app::app(&settings))
+		.disable_signals()
+		.shutdown_timeout(shutdown::DRAIN_TIMEOUT)
+		.client_request_timeout(Duration::from_secs(header_timeout))
+		.keep_alive(keep_alive);
+
+	let server = match listener {
+		Some(listener) => server.listen(listener),
+		None => server.bind(("127.0.0.1", port)),
+	};
+
+	let server = match server {
+		Ok(server) => server.run(),
+		Err(err) => {
+			log::error!("unable to listen on port {}: {}", port, err);
+			exit(shutdown::EXIT_FAILURE)
+		}
+	};
+
+	shutdown::setup_signal_handling(server.handle());
+	if let Some(timeout) = idle_timeout {
+		shutdown::setup_idle_shutdown(server.handle(), Duration::from_secs(timeout));
+	}
+
+	match server.await {
+		Ok(()) => {
+			log::info!("server stopped");
+			exit(shutdown::EXIT_SUCCESS)
+		}
+		Err(err) => {
+			log::error!("server error: {}", err);
+			exit(shutdown::EXIT_FAILURE)
+		}
+	}
+}
+
+/// Parses the value of an argument that was given or has a default, exiting
+/// with a usage error when it is malformed.
+fn parse_arg<T: FromStr>(matches: &clap::ArgMatches, name: &str) -> T {
+	let value = matches.get_one::<String>(name).unwrap();
+	match value.parse::<T>() {
+		Ok(parsed) => parsed,
+		Err(_) => {
+			eprintln!("Invalid {}: {}", name, value);
+			exit(shutdown::EXIT_USAGE)
+		}
+	}
 }
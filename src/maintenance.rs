@@ -0,0 +1,59 @@
+use actix_web::http::header;
+use actix_web::web::Bytes;
+use actix_web::HttpResponse;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::paths;
+
+/// The page sent as the body of maintenance responses when the served
+/// directory has one.
+const PAGE: &str = "503.html";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// The contents of `PAGE`, read whenever maintenance mode is switched on so
+/// requests needn't touch the disk.
+static BODY: Mutex<Option<Bytes>> = Mutex::new(None);
+
+pub fn enable() {
+	load_page();
+	ENABLED.store(true, Ordering::SeqCst);
+}
+
+/// Switches maintenance mode on or off and logs the new state.
+pub fn toggle() {
+	if !ENABLED.load(Ordering::SeqCst) {
+		load_page();
+	}
+	let enabled = !ENABLED.fetch_xor(true, Ordering::SeqCst);
+	log::info!(
+		"maintenance mode {}",
+		if enabled { "enabled" } else { "disabled" }
+	);
+}
+
+/// Reads `PAGE` from the first overlay or the served directory that has it.
+fn load_page() {
+	let page = fs::read(paths::locate(Path::new(PAGE))).ok();
+	*BODY.lock().unwrap() = page.map(Bytes::from);
+}
+
+/// While maintenance mode is on, the `503` with a `Retry-After` of
+/// `retry_after` seconds every request is answered with, with the served
+/// directory's `503.html` as the body if there is one.
+pub fn check(retry_after: u64) -> Option<HttpResponse> {
+	if !ENABLED.load(Ordering::SeqCst) {
+		return None;
+	}
+
+	let mut res = HttpResponse::ServiceUnavailable();
+	res.insert_header((header::RETRY_AFTER, retry_after));
+	let res = match BODY.lock().unwrap().clone() {
+		Some(page) => res.content_type("text/html; charset=utf-8").body(page),
+		None => res.finish(),
+	};
+	Some(res)
+}
@@ -0,0 +1,125 @@
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::{header, Method, StatusCode};
+use actix_web::HttpResponse;
+
+use crate::list;
+
+/// The methods accepted in read-only mode, which are also the methods a
+/// static resource can be requested with.
+pub const READ_ONLY: [Method; 3] = [Method::GET, Method::HEAD, Method::OPTIONS];
+
+/// The `Allow` header sent with every `405` and `OPTIONS` response: the
+/// methods a static resource can be requested with, narrowed to `allowed` if
+/// set, so it never lists a method that would be rejected.
+pub fn allow_header(allowed: Option<&[Method]>) -> String {
+	READ_ONLY
+		.iter()
+		.filter(|method| allowed.is_none_or(|allowed| allowed.contains(method)))
+		.map(Method::as_str)
+		.collect::<Vec<_>>()
+		.join(", ")
+}
+
+/// `Files` resolves paths for every method, so the outcome tells whether the
+/// resource exists. For anything but `GET` and `HEAD`, an existing resource
+/// answers `OPTIONS` with a `204` and other methods with a `405`, both
+/// listing the methods in `allow`. Missing resources keep their `404`.
+pub fn restrict_to_reads(res: ServiceResponse, allow: &str) -> ServiceResponse {
+	let method = res.request().method();
+	if method == Method::GET || method == Method::HEAD {
+		return res;
+	}
+	if res.status().is_client_error() || res.status().is_server_error() {
+		return res;
+	}
+
+	let status = if method == Method::OPTIONS {
+		StatusCode::NO_CONTENT
+	} else {
+		StatusCode::METHOD_NOT_ALLOWED
+	};
+
+	let (req, _) = res.into_parts();
+	let res = HttpResponse::build(status)
+		.insert_header((header::ALLOW, allow))
+		.finish();
+	ServiceResponse::new(req, res)
+}
+
+/// The methods defined by HTTP, the only ones `--allow-methods` accepts so a
+/// typo such as `HAED` is reported rather than silently allowed.
+const STANDARD: [Method; 9] = [
+	Method::GET,
+	Method::HEAD,
+	Method::POST,
+	Method::PUT,
+	Method::DELETE,
+	Method::CONNECT,
+	Method::OPTIONS,
+	Method::TRACE,
+	Method::PATCH,
+];
+
+/// Parses a comma separated list of methods such as `GET,HEAD`.
+pub fn parse_list(list: &str) -> Result<Vec<Method>, String> {
+	list::parse(list, |name| {
+		STANDARD
+			.into_iter()
+			.find(|method| method.as_str().eq_ignore_ascii_case(name))
+	})
+}
+
+/// The `405` a request whose method isn't in `allowed` is rejected with
+/// before it is routed, listing the methods in `allow`.
+pub fn check_allowed(req: &ServiceRequest, allowed: &[Method], allow: &str) -> Option<HttpResponse> {
+	if allowed.contains(req.method()) {
+		return None;
+	}
+
+	let res = HttpResponse::MethodNotAllowed()
+		.insert_header((header::ALLOW, allow))
+		.finish();
+	Some(res)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use actix_web::test::TestRequest;
+
+	#[test]
+	fn parses_methods_in_any_case() {
+		assert_eq!(parse_list("get, Head"), Ok(vec![Method::GET, Method::HEAD]));
+	}
+
+	#[test]
+	fn rejects_unknown_methods() {
+		assert_eq!(parse_list("GET,HAED"), Err(String::from("HAED")));
+	}
+
+	#[test]
+	fn parses_empty_lists_as_empty() {
+		assert_eq!(parse_list(""), Ok(Vec::new()));
+		assert_eq!(parse_list(" , "), Ok(Vec::new()));
+	}
+
+	#[test]
+	fn narrows_the_allow_header() {
+		assert_eq!(allow_header(None), "GET, HEAD, OPTIONS");
+		assert_eq!(allow_header(Some(&[Method::GET, Method::POST])), "GET");
+	}
+
+	#[test]
+	fn rejects_methods_outside_the_allowlist() {
+		let allowed = [Method::GET, Method::HEAD];
+		let allow = allow_header(Some(&allowed));
+
+		let post = TestRequest::post().to_srv_request();
+		let res = check_allowed(&post, &allowed, &allow).unwrap();
+		assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+		assert_eq!(res.headers().get(header::ALLOW).unwrap(), "GET, HEAD");
+
+		let get = TestRequest::get().to_srv_request();
+		assert!(check_allowed(&get, &allowed, &allow).is_none());
+	}
+}
@@ -0,0 +1,71 @@
+use actix_web::dev::ServiceRequest;
+use actix_web::http::header::{Accept, Header};
+use actix_web::http::Uri;
+use actix_web::HttpMessage;
+
+use crate::paths;
+
+/// Whether the client ranks JSON above anything else it accepts.
+pub fn prefers_json<M: HttpMessage>(msg: &M) -> bool {
+	match Accept::parse(msg) {
+		Ok(accept) => accept.preference().essence_str() == "application/json",
+		Err(_) => false,
+	}
+}
+
+/// Points a request for a directory at its `index.json` when the client
+/// prefers JSON and the directory has one. Other clients keep getting the
+/// regular index file.
+///
+/// Returns whether the request was for a directory, as the response then
+/// depends on the `Accept` header.
+pub fn index_json(req: &mut ServiceRequest) -> bool {
+	let dir = match paths::normalize_request_path(req.path()) {
+		Some(path) => path,
+		None => return false,
+	};
+	if !paths::locate(&dir).is_dir() {
+		return false;
+	}
+	if !prefers_json(req) || !paths::locate(&dir.join("index.json")).is_file() {
+		return true;
+	}
+
+	let mut target = format!("{}/index.json", req.path().trim_end_matches('/'));
+	if let Some(query) = req.uri().query() {
+		target.push('?');
+		target.push_str(query);
+	}
+	if let Ok(uri) = target.parse::<Uri>() {
+		req.match_info_mut().get_mut().update(&uri);
+		req.head_mut().uri = uri;
+	}
+
+	true
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use actix_web::http::header;
+	use actix_web::test::TestRequest;
+
+	fn accepting(accept: &str) -> actix_web::HttpRequest {
+		TestRequest::default()
+			.insert_header((header::ACCEPT, accept))
+			.to_http_request()
+	}
+
+	#[test]
+	fn prefers_json_when_ranked_first() {
+		assert!(prefers_json(&accepting("application/json")));
+		assert!(prefers_json(&accepting("text/html;q=0.5, application/json")));
+	}
+
+	#[test]
+	fn prefers_html_otherwise() {
+		assert!(!prefers_json(&accepting("text/html, application/json;q=0.9")));
+		assert!(!prefers_json(&accepting("*/*")));
+		assert!(!prefers_json(&TestRequest::default().to_http_request()));
+	}
+}
@@ -0,0 +1,147 @@
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::http::StatusCode;
+use actix_web::HttpResponse;
+use percent_encoding::percent_decode_str;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+static OVERLAYS: OnceLock<Vec<PathBuf>> = OnceLock::new();
+
+/// Decodes a raw, percent-encoded request path into a path relative to the
+/// served directory.
+///
+/// Returns `None` for anything that could step outside of it or that the
+/// filesystem may interpret differently than intended: `..` segments, whether
+/// plain or encoded, encoded slashes, backslashes, null bytes and invalid
+/// UTF-8.
+pub fn normalize_request_path(raw: &str) -> Option<PathBuf> {
+	let decoded = percent_decode_str(raw).decode_utf8().ok()?;
+	if decoded.contains('\0') || decoded.contains('\\') {
+		return None;
+	}
+	if decoded.matches('/').count() != raw.matches('/').count() {
+		return None;
+	}
+
+	let mut path = PathBuf::new();
+	for segment in decoded.split('/') {
+		match segment {
+			"" | "." => continue,
+			".." => return None,
+			segment => path.push(segment),
+		}
+	}
+
+	Some(path)
+}
+
+/// Sets the directories searched before the served one, see `--overlay`.
+pub fn set_overlays(dirs: Vec<PathBuf>) {
+	OVERLAYS.get_or_init(|| dirs);
+}
+
+/// Finds a path relative to the served directory in the first overlay that
+/// has it, or else in the served directory itself.
+pub fn locate(path: &Path) -> PathBuf {
+	OVERLAYS
+		.get()
+		.into_iter()
+		.flatten()
+		.map(|dir| dir.join(path))
+		.find(|candidate| candidate.exists())
+		.unwrap_or_else(|| Path::new(".").join(path))
+}
+
+/// Maps a request path onto the file `Files` would serve for it.
+pub fn resolve_file(request_path: &str, index_file: &str) -> Option<PathBuf> {
+	let path = normalize_request_path(request_path)?;
+	let file = locate(&path);
+	if file.is_dir() {
+		return Some(locate(&path.join(index_file)));
+	}
+
+	Some(file)
+}
+
+/// The `403` a request whose path doesn't normalize is rejected with, instead
+/// of leaving it to actix-files, which answers such paths with a mix of 400s
+/// and 404s.
+pub fn check_request_path(req: &ServiceRequest) -> Option<HttpResponse> {
+	if normalize_request_path(req.path()).is_some() {
+		return None;
+	}
+
+	log::debug!("rejecting unsafe request path {}", req.path());
+	Some(HttpResponse::Forbidden().finish())
+}
+
+/// Adds the request's query string to the trailing-slash redirect actix-files
+/// sends for a directory, which it builds from the path alone.
+pub fn keep_query<B>(res: &mut ServiceResponse<B>) {
+	if res.status() != StatusCode::FOUND {
+		return;
+	}
+	let query = match res.request().uri().query() {
+		Some(query) => query.to_string(),
+		None => return,
+	};
+	let slash = format!("{}/", res.request().path());
+	let is_slash_redirect = res
+		.headers()
+		.get(header::LOCATION)
+		.is_some_and(|location| location == slash.as_str());
+
+	if is_slash_redirect {
+		if let Ok(location) = HeaderValue::from_str(&format!("{}?{}", slash, query)) {
+			res.headers_mut().insert(header::LOCATION, location);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn normalizes_plain_paths() {
+		assert_eq!(normalize_request_path("/"), Some(PathBuf::new()));
+		assert_eq!(
+			normalize_request_path("/docs/./a%20b.html"),
+			Some(PathBuf::from("docs/a b.html"))
+		);
+	}
+
+	#[test]
+	fn rejects_parent_segments() {
+		assert_eq!(normalize_request_path("/../etc/passwd"), None);
+		assert_eq!(normalize_request_path("/docs/.."), None);
+	}
+
+	#[test]
+	fn rejects_encoded_traversal() {
+		assert_eq!(normalize_request_path("/%2e%2e%2fetc/passwd"), None);
+		assert_eq!(normalize_request_path("/%2E%2E/etc/passwd"), None);
+	}
+
+	#[test]
+	fn rejects_encoded_slashes() {
+		assert_eq!(normalize_request_path("/docs%2findex.html"), None);
+	}
+
+	#[test]
+	fn rejects_backslashes() {
+		assert_eq!(normalize_request_path("/\\..\\etc\\passwd"), None);
+		assert_eq!(normalize_request_path("/%5c..%5cetc"), None);
+	}
+
+	#[test]
+	fn rejects_null_bytes() {
+		assert_eq!(normalize_request_path("/index.html%00.txt"), None);
+	}
+
+	#[test]
+	fn rejects_invalid_utf8() {
+		assert_eq!(normalize_request_path("/%ff"), None);
+	}
+}
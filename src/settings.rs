@@ -0,0 +1,168 @@
+use actix_web::http::Method;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+use crate::access::Cidr;
+
+/// The effective settings msaada serves with, resolved from the command line.
+#[derive(Clone)]
+pub struct Settings {
+	/// The served directory, or the single served file.
+	pub root: PathBuf,
+	pub port: u16,
+	/// The URL shown to the user, see `--public-url`.
+	pub url: String,
+	pub index_file: String,
+	pub single_file: bool,
+	pub index_redirect: bool,
+	pub source_maps: bool,
+	pub header_timeout: u64,
+	/// When set, requests with any other method get a `405`.
+	pub allowed_methods: Option<Vec<Method>>,
+	pub read_only: bool,
+	pub no_cache_html: bool,
+	/// Serve `index.json` for directories to clients that prefer JSON.
+	pub index_json: bool,
+	/// The file served for `GET` requests that match no file.
+	pub fallback: Option<PathBuf>,
+	/// The default language `page.LANG.html` variants are negotiated with,
+	/// see `--language`.
+	pub language: Option<String>,
+	/// Seconds sent in `Retry-After` while in maintenance mode.
+	pub retry_after: u64,
+	/// Hidden paths served besides `.well-known`.
+	pub allowed_dotfiles: Vec<PathBuf>,
+	pub strip_bom: bool,
+	/// Bytes per second response bodies are sent at, see `--throttle`.
+	pub throttle_rate: Option<u64>,
+	/// When set, clients outside these ranges get a `403`.
+	pub allowed_ips: Option<Vec<Cidr>>,
+	pub denied_ips: Vec<Cidr>,
+	/// Seconds without requests after which the server stops.
+	pub idle_timeout: Option<u64>,
+	pub secure_headers: bool,
+	pub nosniff: bool,
+	/// Added to `text/*` content types without a charset.
+	pub default_charset: Option<String>,
+	/// Charsets set per content type, see `--charset`.
+	pub charsets: Vec<(String, String)>,
+	/// Directories searched before the served one, in order.
+	pub overlays: Vec<PathBuf>,
+	pub keep_alive: bool,
+	/// Serve the settings as JSON at `/__msaada/info`.
+	pub introspect: bool,
+	/// Bytes a request line and headers may take before a `431`.
+	pub max_header_size: Option<usize>,
+	pub access_log_format: String,
+}
+
+impl Settings {
+	pub fn log(&self) {
+		log::info!("effective configuration:");
+		log::info!("  root: {}", self.root.display());
+		log::info!("  port: {}", self.port);
+		log::info!("  url: {}", self.url);
+		log::info!("  index file: {}", self.index_file);
+		log::info!("  single file: {}", self.single_file);
+		log::info!("  index redirect: {}", self.index_redirect);
+		log::info!("  source maps: {}", self.source_maps);
+		log::info!("  header timeout: {}s", self.header_timeout);
+		match &self.allowed_methods {
+			Some(methods) => log::info!("  allowed methods: {:?}", methods),
+			None => log::info!("  allowed methods: all"),
+		}
+		log::info!("  read only: {}", self.read_only);
+		log::info!("  no-cache html: {}", self.no_cache_html);
+		log::info!("  index json: {}", self.index_json);
+		match &self.fallback {
+			Some(file) => log::info!("  fallback: {}", file.display()),
+			None => log::info!("  fallback: none"),
+		}
+		match &self.language {
+			Some(language) => log::info!("  language negotiation: default {}", language),
+			None => log::info!("  language negotiation: off"),
+		}
+		log::info!("  retry after: {}s", self.retry_after);
+		log::info!("  allowed dotfiles: {:?}", self.allowed_dotfiles);
+		log::info!("  strip bom: {}", self.strip_bom);
+		match self.throttle_rate {
+			Some(rate) => log::info!("  throttle: {} bytes/s", rate),
+			None => log::info!("  throttle: none"),
+		}
+		match &self.allowed_ips {
+			Some(ips) => log::info!("  allowed ips: {:?}", ips),
+			None => log::info!("  allowed ips: all"),
+		}
+		log::info!("  denied ips: {:?}", self.denied_ips);
+		match self.idle_timeout {
+			Some(timeout) => log::info!("  shutdown on idle: {}s", timeout),
+			None => log::info!("  shutdown on idle: never"),
+		}
+		log::info!("  secure headers: {}", self.secure_headers);
+		log::info!("  nosniff: {}", self.nosniff);
+		match &self.default_charset {
+			Some(charset) => log::info!("  default charset: {}", charset),
+			None => log::info!("  default charset: none"),
+		}
+		log::info!("  charsets: {:?}", self.charsets);
+		log::info!("  overlays: {:?}", self.overlays);
+		log::info!("  keep-alive: {}", self.keep_alive);
+		log::info!("  introspect: {}", self.introspect);
+		match self.max_header_size {
+			Some(max) => log::info!("  max header size: {} bytes", max),
+			None => log::info!("  max header size: default"),
+		}
+		log::info!("  access log format: {}", self.access_log_format);
+	}
+
+	/// The settings as reported by `--introspect`.
+	pub fn to_json(&self) -> Value {
+		let paths = |paths: &[PathBuf]| paths.iter().map(|path| display(path)).collect::<Vec<_>>();
+		let ranges = |cidrs: &[Cidr]| cidrs.iter().map(Cidr::to_string).collect::<Vec<_>>();
+		let charsets = self
+			.charsets
+			.iter()
+			.map(|(content_type, charset)| format!("{}={}", content_type, charset))
+			.collect::<Vec<_>>();
+
+		json!({
+			"version": env!("CARGO_PKG_VERSION"),
+			"root": display(&self.root),
+			"port": self.port,
+			"url": self.url,
+			"index_file": self.index_file,
+			"single_file": self.single_file,
+			"index_redirect": self.index_redirect,
+			"source_maps": self.source_maps,
+			"header_timeout": self.header_timeout,
+			"allowed_methods": self.allowed_methods.as_ref().map(|methods| {
+				methods.iter().map(Method::as_str).collect::<Vec<_>>()
+			}),
+			"read_only": self.read_only,
+			"no_cache_html": self.no_cache_html,
+			"index_json": self.index_json,
+			"fallback": self.fallback.as_deref().map(display),
+			"language": self.language,
+			"retry_after": self.retry_after,
+			"allowed_dotfiles": paths(&self.allowed_dotfiles),
+			"strip_bom": self.strip_bom,
+			"throttle_rate": self.throttle_rate,
+			"allowed_ips": self.allowed_ips.as_deref().map(ranges),
+			"denied_ips": ranges(&self.denied_ips),
+			"idle_timeout": self.idle_timeout,
+			"secure_headers": self.secure_headers,
+			"nosniff": self.nosniff,
+			"default_charset": self.default_charset,
+			"charsets": charsets,
+			"overlays": paths(&self.overlays),
+			"keep_alive": self.keep_alive,
+			"introspect": self.introspect,
+			"max_header_size": self.max_header_size,
+			"access_log_format": self.access_log_format,
+		})
+	}
+}
+
+fn display(path: &Path) -> String {
+	path.display().to_string()
+}
@@ -0,0 +1,128 @@
+use actix_web::dev::ServerHandle;
+use actix_web::rt;
+use std::process::exit;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::settings::Settings;
+use crate::stats;
+
+/// Exit code for a clean shutdown.
+pub const EXIT_SUCCESS: i32 = 0;
+/// Exit code for runtime failures, e.g. the port could not be bound.
+pub const EXIT_FAILURE: i32 = 1;
+/// Exit code for invalid command line arguments, matching clap's own usage errors.
+pub const EXIT_USAGE: i32 = 2;
+
+/// Seconds in-flight requests are given to finish once shutdown starts.
+pub const DRAIN_TIMEOUT: u64 = 5;
+
+/// How often the server checks whether it has been idle for too long.
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// Listens for termination signals and stops the server gracefully.
+///
+/// Takes over from actix-web's built-in signal handling, so the server must be
+/// built with `disable_signals()`.
+#[cfg(unix)]
+pub fn setup_signal_handling(server: ServerHandle) {
+	use actix_web::rt::signal::unix::SignalKind;
+
+	let signals = [
+		(SignalKind::interrupt(), "SIGINT"),
+		(SignalKind::terminate(), "SIGTERM"),
+		(SignalKind::quit(), "SIGQUIT"),
+	];
+
+	for (kind, name) in signals {
+		let server = server.clone();
+		on_signal(kind, name, move || shutdown(name, &server));
+	}
+}
+
+/// Listens for Ctrl-C and stops the server gracefully.
+#[cfg(not(unix))]
+pub fn setup_signal_handling(server: ServerHandle) {
+	rt::spawn(async move {
+		while rt::signal::ctrl_c().await.is_ok() {
+			shutdown("Ctrl-C", &server);
+		}
+	});
+}
+
+/// Logs the server stats and effective settings on `SIGUSR1`.
+#[cfg(unix)]
+pub fn setup_stats_signal(settings: Settings) {
+	use actix_web::rt::signal::unix::SignalKind;
+
+	on_signal(SignalKind::user_defined1(), "SIGUSR1", move || {
+		stats::log(&settings)
+	});
+}
+
+#[cfg(not(unix))]
+pub fn setup_stats_signal(_settings: Settings) {}
+
+/// Toggles maintenance mode on `SIGUSR2`.
+#[cfg(unix)]
+pub fn setup_maintenance_signal() {
+	use actix_web::rt::signal::unix::SignalKind;
+
+	on_signal(SignalKind::user_defined2(), "SIGUSR2", crate::maintenance::toggle);
+}
+
+#[cfg(not(unix))]
+pub fn setup_maintenance_signal() {}
+
+/// Stops the server gracefully once it has gone `timeout` without requests.
+pub fn setup_idle_shutdown(server: ServerHandle, timeout: Duration) {
+	rt::spawn(async move {
+		loop {
+			rt::time::sleep(IDLE_CHECK_INTERVAL).await;
+			if stats::idle_for().is_some_and(|idle| idle >= timeout) {
+				break;
+			}
+		}
+
+		if !SHUTTING_DOWN.swap(true, Ordering::SeqCst) {
+			log::info!("no requests for {}s, shutting down", timeout.as_secs());
+			server.stop(true).await;
+		}
+	});
+}
+
+#[cfg(unix)]
+fn on_signal<F>(kind: rt::signal::unix::SignalKind, name: &'static str, handler: F)
+where
+	F: Fn() + 'static,
+{
+	rt::spawn(async move {
+		let mut stream = match rt::signal::unix::signal(kind) {
+			Ok(stream) => stream,
+			Err(err) => {
+				log::warn!("unable to listen for {}: {}", name, err);
+				return;
+			}
+		};
+		while stream.recv().await.is_some() {
+			handler();
+		}
+	});
+}
+
+/// The first signal starts a graceful drain, a second one while draining
+/// forces an immediate exit so a stuck client can't hold up the process.
+fn shutdown(reason: &str, server: &ServerHandle) {
+	if SHUTTING_DOWN.swap(true, Ordering::SeqCst) {
+		log::warn!("received {} again, forcing exit", reason);
+		exit(EXIT_FAILURE);
+	}
+
+	log::info!(
+		"received {}, shutting down gracefully... (repeat to force quit)",
+		reason
+	);
+	rt::spawn(server.stop(true));
+}
@@ -0,0 +1,20 @@
+use actix_web::dev::ServiceResponse;
+use actix_web::http::header::{self, HeaderValue};
+use std::path::Path;
+
+pub fn is_source_map(path: &Path) -> bool {
+	path.extension().is_some_and(|ext| ext == "map")
+}
+
+/// Serves source maps as `application/json`, which is what browser devtools
+/// expect. actix-files would otherwise guess `text/plain` for `.map`.
+pub fn set_content_type<B>(res: &mut ServiceResponse<B>) {
+	if !res.status().is_success() || !is_source_map(Path::new(res.request().path())) {
+		return;
+	}
+
+	res.headers_mut().insert(
+		header::CONTENT_TYPE,
+		HeaderValue::from_static("application/json"),
+	);
+}
@@ -0,0 +1,113 @@
+use actix_web::body::{BodySize, BoxBody, MessageBody};
+use actix_web::dev::ServiceResponse;
+use actix_web::web::Bytes;
+use std::error::Error;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use crate::settings::Settings;
+
+static STARTED: OnceLock<Instant> = OnceLock::new();
+static REQUESTS: AtomicU64 = AtomicU64::new(0);
+/// Requests still being handled or having their response body sent.
+static IN_FLIGHT: AtomicU64 = AtomicU64::new(0);
+/// Milliseconds after the start at which a request last began or ended.
+static LAST_ACTIVITY: AtomicU64 = AtomicU64::new(0);
+
+/// Marks the moment the server started, for reporting uptime.
+pub fn start() {
+	STARTED.get_or_init(Instant::now);
+}
+
+/// Counts a request as handled and as in flight until the guard is dropped,
+/// which [`track`] delays until the response body has been sent.
+pub fn track_request() -> RequestGuard {
+	REQUESTS.fetch_add(1, Ordering::Relaxed);
+	IN_FLIGHT.fetch_add(1, Ordering::Relaxed);
+	mark_activity();
+	RequestGuard
+}
+
+pub struct RequestGuard;
+
+/// Keeps `guard` alive until the body of `res` has been sent or dropped, as
+/// the response head comes back long before a large or throttled body ends.
+pub fn track<B>(res: ServiceResponse<B>, guard: RequestGuard) -> ServiceResponse
+where
+	B: MessageBody + 'static,
+{
+	res.map_into_boxed_body().map_body(|_, body| {
+		BoxBody::new(Tracked {
+			body,
+			guard: Some(guard),
+		})
+	})
+}
+
+/// A body that releases its request guard once it ends.
+struct Tracked {
+	body: BoxBody,
+	guard: Option<RequestGuard>,
+}
+
+impl MessageBody for Tracked {
+	type Error = Box<dyn Error>;
+
+	fn size(&self) -> BodySize {
+		self.body.size()
+	}
+
+	fn poll_next(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+	) -> Poll<Option<Result<Bytes, Self::Error>>> {
+		let next = Pin::new(&mut self.body).poll_next(cx);
+		if let Poll::Ready(None | Some(Err(_))) = next {
+			self.guard = None;
+		}
+		next
+	}
+}
+
+impl Drop for RequestGuard {
+	fn drop(&mut self) {
+		IN_FLIGHT.fetch_sub(1, Ordering::Relaxed);
+		mark_activity();
+	}
+}
+
+fn mark_activity() {
+	if let Some(started) = STARTED.get() {
+		LAST_ACTIVITY.store(started.elapsed().as_millis() as u64, Ordering::Relaxed);
+	}
+}
+
+/// How long the server has been without requests, or `None` while one is in
+/// flight.
+pub fn idle_for() -> Option<Duration> {
+	let started = STARTED.get()?;
+	if IN_FLIGHT.load(Ordering::Relaxed) > 0 {
+		return None;
+	}
+
+	let last = Duration::from_millis(LAST_ACTIVITY.load(Ordering::Relaxed));
+	Some(started.elapsed().saturating_sub(last))
+}
+
+pub fn log(settings: &Settings) {
+	let uptime = STARTED
+		.get()
+		.map_or(0, |started| started.elapsed().as_secs());
+
+	log::info!("server stats:");
+	log::info!("  uptime: {}s", uptime);
+	log::info!("  requests handled: {}", REQUESTS.load(Ordering::Relaxed));
+	log::info!(
+		"  requests in flight: {}",
+		IN_FLIGHT.load(Ordering::Relaxed)
+	);
+	settings.log();
+}
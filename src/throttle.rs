@@ -0,0 +1,136 @@
+use actix_web::body::{BodySize, BoxBody, MessageBody};
+use actix_web::dev::ServiceResponse;
+use actix_web::rt::time::{sleep, Sleep};
+use actix_web::web::Bytes;
+use std::error::Error;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// Times per second data is sent, so progress moves smoothly at low rates.
+const TICKS_PER_SECOND: u64 = 10;
+
+/// Parses a bandwidth such as `512kbps` or `1mbps`, in bits per second like
+/// network tools use, into bytes per second.
+pub fn parse_rate(rate: &str) -> Option<u64> {
+	let rate = rate.trim().to_ascii_lowercase();
+	let (number, bits) = if let Some(number) = rate.strip_suffix("gbps") {
+		(number, 1_000_000_000)
+	} else if let Some(number) = rate.strip_suffix("mbps") {
+		(number, 1_000_000)
+	} else if let Some(number) = rate.strip_suffix("kbps") {
+		(number, 1_000)
+	} else {
+		(rate.strip_suffix("bps")?, 1)
+	};
+
+	let bytes = (number.trim().parse::<f64>().ok()? * bits as f64 / 8.0) as u64;
+	if bytes == 0 {
+		return None;
+	}
+
+	Some(bytes)
+}
+
+/// Sends the body of `res` at no more than `rate` bytes per second, to
+/// simulate a slow connection.
+pub fn limit(res: ServiceResponse, rate: u64) -> ServiceResponse {
+	res.map_body(|_, body| {
+		BoxBody::new(Throttle {
+			body,
+			rate,
+			pending: Bytes::new(),
+			delay: None,
+		})
+	})
+}
+
+struct Throttle {
+	body: BoxBody,
+	rate: u64,
+	/// What is left of the last chunk of `body`.
+	pending: Bytes,
+	/// Runs until the data sent so far is within the rate.
+	delay: Option<Pin<Box<Sleep>>>,
+}
+
+impl MessageBody for Throttle {
+	type Error = Box<dyn Error>;
+
+	fn size(&self) -> BodySize {
+		self.body.size()
+	}
+
+	fn poll_next(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+	) -> Poll<Option<Result<Bytes, Self::Error>>> {
+		if let Some(delay) = &mut self.delay {
+			if delay.as_mut().poll(cx).is_pending() {
+				return Poll::Pending;
+			}
+			self.delay = None;
+		}
+
+		while self.pending.is_empty() {
+			match Pin::new(&mut self.body).poll_next(cx) {
+				Poll::Ready(Some(Ok(chunk))) => self.pending = chunk,
+				other => return other,
+			}
+		}
+
+		let len = self
+			.pending
+			.len()
+			.min((self.rate / TICKS_PER_SECOND).max(1) as usize);
+		let piece = self.pending.split_to(len);
+		let wait = Duration::from_secs_f64(len as f64 / self.rate as f64);
+		self.delay = Some(Box::pin(sleep(wait)));
+
+		Poll::Ready(Some(Ok(piece)))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_rates_in_bits() {
+		assert_eq!(parse_rate("1mbps"), Some(125_000));
+		assert_eq!(parse_rate("1.5kbps"), Some(187));
+		assert_eq!(parse_rate(" 2 GBPS "), Some(250_000_000));
+	}
+
+	#[test]
+	fn rejects_rates_below_a_byte() {
+		assert_eq!(parse_rate("0bps"), None);
+		assert_eq!(parse_rate("-1mbps"), None);
+	}
+
+	#[actix_web::test]
+	async fn paces_the_body() {
+		let body = Throttle {
+			body: BoxBody::new(Bytes::from(vec![0; 300])),
+			rate: 1_000,
+			pending: Bytes::new(),
+			delay: None,
+		};
+
+		let start = std::time::Instant::now();
+		let sent = actix_web::body::to_bytes(body).await.unwrap();
+		let elapsed = start.elapsed();
+
+		assert_eq!(sent.len(), 300);
+		assert!(elapsed >= Duration::from_millis(280), "took {:?}", elapsed);
+		assert!(elapsed < Duration::from_secs(1), "took {:?}", elapsed);
+	}
+
+	#[test]
+	fn rejects_malformed_rates() {
+		assert_eq!(parse_rate("nan"), None);
+		assert_eq!(parse_rate("nanbps"), None);
+		assert_eq!(parse_rate("fast"), None);
+	}
+}